@@ -0,0 +1,558 @@
+/* machine configuration, from the command line and from a profile file - dream86 */
+
+use std::fs;
+use std::process;
+
+use crate::machine::machineType;
+use crate::videoScaler::{scaleMode, crtFilter, MAX_ZOOM};
+use crate::guiif::joystickSource;
+use crate::emuLog;
+
+pub const SYNTAX:&str="syntax: dream86 [options] [disk image]
+
+  --config <file>        machine profile, options as \"name = value\" lines (the command line wins)
+  --fda <image>          floppy image for A:
+  --fdb <image>          floppy image for B:
+  --hda <image>          hard disk image for C:
+  --hdb <image>          hard disk image for D:
+  --boot a|c             boot drive (default A: if there's a floppy, else C:)
+  --com <file>           run a .com program instead of booting
+  --bin <file>           run a .bin test from artlav's test suite instead of booting
+  --bios <file>          BIOS image (default ./bios/bios_cga)
+  --machine <type>       cga, ega, vga (the same as ega), mda or hercules (default ega)
+  --ram <KB>             conventional memory, 64 to 640 (default 640)
+  --speed <n>|max        instructions per second (default max)
+  --debug                start in the debugger instead of running
+  --headless             no window, run until a stop condition and print the final state
+  --terminal             no window, show the text screen in the terminal
+  --monitor rgb|composite
+  --scale aspect|integer
+  --zoom 1-4
+  --filter none|scanlines|crt
+  --border               show the border (overscan) color
+  --joystick-a <source>  keys (the numeric keypad, default), mouse or none
+  --joystick-b <source>  keys, mouse (default) or none
+  --printer <file>       LPT1 output file
+  --printer-mode raw|text
+  --record <file>        record a y4m video from power on
+  --max-instructions <n> headless: stop after n instructions
+  --stop-at <cs:ip>      headless: stop at this address (hex)
+  --stop-at-hlt          headless: stop at the first HLT
+  --stop-text <text>     headless: stop when this text is on screen
+  --png <file>           headless: save the last frame
+  --text-dump <file>     headless: save the last text screen";
+
+pub struct config
+{
+    pub fdaImage: String,
+    pub fdbImage: String,
+    pub hdaImage: String,
+    pub hdbImage: String,
+    pub bootDrive: Option<u8>,
+    pub comFile: String,
+    pub binFile: String,
+    pub biosFile: String,
+    pub machType: machineType,
+    pub ramKb: u16,
+    pub speed: u64,
+    pub debug: bool,
+    pub headless: bool,
+    pub terminal: bool,
+    pub compositeMonitor: bool,
+    pub scaleMode: scaleMode,
+    pub zoom: u32,
+    pub filter: crtFilter,
+    pub showBorder: bool,
+    pub joystickA: joystickSource,
+    pub joystickB: joystickSource,
+    pub printerFile: String,
+    pub printerText: bool,
+    pub videoFile: String,
+    pub maxInstructions: u64,
+    pub stopAddress: Option<(u16,u16)>,
+    pub stopOnHlt: bool,
+    pub stopText: String,
+    pub pngFile: String,
+    pub textFile: String
+}
+
+impl config
+{
+    pub fn new() -> Self
+    {
+        config
+        {
+            fdaImage: String::from(""),
+            fdbImage: String::from(""),
+            hdaImage: String::from(""),
+            hdbImage: String::from(""),
+            bootDrive: None,
+            comFile: String::from(""),
+            binFile: String::from(""),
+            biosFile: String::from("./bios/bios_cga"),
+            machType: machineType::machineEGA,
+            ramKb: 640,
+            speed: 0,
+            debug: false,
+            headless: false,
+            terminal: false,
+            compositeMonitor: false,
+            scaleMode: scaleMode::scaleAspect,
+            zoom: 1,
+            filter: crtFilter::filterNone,
+            showBorder: false,
+            joystickA: joystickSource::joyKeys,
+            joystickB: joystickSource::joyMouse,
+            printerFile: String::from(""),
+            printerText: false,
+            videoFile: String::from(""),
+            maxInstructions: 0,
+            stopAddress: None,
+            stopOnHlt: false,
+            stopText: String::from(""),
+            pngFile: String::from(""),
+            textFile: String::from("")
+        }
+    }
+
+    fn abort(msg:&str) -> !
+    {
+        emuLog::fatalError(msg,emuLog::EXIT_BAD_CONFIG);
+    }
+
+    // options that are switched on just by being there on the command line
+    fn isFlagOption(name:&str) -> bool
+    {
+        return (name=="debug") || (name=="headless") || (name=="terminal") || (name=="border") || (name=="stop-at-hlt");
+    }
+
+    fn parseBool(name:&str,value:&str) -> bool
+    {
+        if (value=="true") || (value=="on") || (value=="yes") || (value=="1") { return true; }
+        else if (value=="false") || (value=="off") || (value=="no") || (value=="0") { return false; }
+
+        Self::abort(&format!("Invalid value {} for {}, use true or false",value,name));
+    }
+
+    fn parseNumber(name:&str,value:&str) -> u64
+    {
+        return match value.parse::<u64>() {
+            Ok(n) => n,
+            Err(_) => Self::abort(&format!("Invalid number {} for {}",value,name)),
+        };
+    }
+
+    // first image that can be booted, when there's no --boot
+    pub fn getBootDrive(&self) -> u8
+    {
+        if let Some(drive)=self.bootDrive { return drive; }
+        if (self.fdaImage=="") && (self.hdaImage!="") { return 0x80; }
+        return 0;
+    }
+
+    // 0 boots the BIOS, 1 runs a .com, 2 runs a .bin test
+    pub fn getRunMode(&self) -> u8
+    {
+        if self.binFile!="" { return 2; }
+        else if self.comFile!="" { return 1; }
+        return 0;
+    }
+
+    pub fn getProgramFile(&self) -> String
+    {
+        if self.binFile!="" { return self.binFile.clone(); }
+        return self.comFile.clone();
+    }
+
+    fn parseJoystick(name:&str,value:&str) -> joystickSource
+    {
+        return match value {
+            "keys" => joystickSource::joyKeys,
+            "mouse" => joystickSource::joyMouse,
+            "none" => joystickSource::joyNone,
+            _ => Self::abort(&format!("Unknown joystick {} for {}, use keys, mouse or none",value,name)),
+        };
+    }
+
+    pub fn setOption(&mut self,name:&str,value:&str)
+    {
+        if name=="fda" { self.fdaImage=String::from(value); }
+        else if name=="fdb" { self.fdbImage=String::from(value); }
+        else if name=="hda" { self.hdaImage=String::from(value); }
+        else if name=="hdb" { self.hdbImage=String::from(value); }
+        else if name=="boot"
+        {
+            self.bootDrive=match value {
+                "a" | "A" => Some(0),
+                "c" | "C" => Some(0x80),
+                _ => Self::abort(&format!("Unknown boot drive {}, use a or c",value)),
+            };
+        }
+        else if name=="com" { self.comFile=String::from(value); }
+        else if name=="bin" { self.binFile=String::from(value); }
+        else if name=="bios" { self.biosFile=String::from(value); }
+        else if name=="machine"
+        {
+            self.machType=match value {
+                "cga" => machineType::machineCGA,
+                "ega" | "vga" => machineType::machineEGA,
+                "mda" => machineType::machineMDA,
+                "hercules" => machineType::machineHercules,
+                _ => Self::abort(&format!("Unknown machine type {}",value)),
+            };
+        }
+        else if name=="ram"
+        {
+            let kb=Self::parseNumber(name,value);
+            if (kb<64) || (kb>640)
+            {
+                Self::abort(&format!("Conventional memory must be 64 to 640 KB, not {}",kb));
+            }
+            self.ramKb=kb as u16;
+        }
+        else if name=="speed"
+        {
+            self.speed=if value=="max" { 0 } else { Self::parseNumber(name,value) };
+        }
+        else if name=="debug" { self.debug=Self::parseBool(name,value); }
+        else if name=="headless" { self.headless=Self::parseBool(name,value); }
+        else if name=="terminal" { self.terminal=Self::parseBool(name,value); }
+        else if name=="monitor"
+        {
+            if (value!="rgb") && (value!="composite") { Self::abort(&format!("Unknown monitor {}",value)); }
+            self.compositeMonitor=value=="composite";
+        }
+        else if name=="scale"
+        {
+            self.scaleMode=match value {
+                "aspect" => scaleMode::scaleAspect,
+                "integer" => scaleMode::scaleInteger,
+                _ => Self::abort(&format!("Unknown scale mode {}",value)),
+            };
+        }
+        else if name=="zoom"
+        {
+            let zoom=Self::parseNumber(name,value);
+            if (zoom<1) || (zoom>(MAX_ZOOM as u64)) { Self::abort(&format!("Invalid zoom {}, use 1 to {}",value,MAX_ZOOM)); }
+            self.zoom=zoom as u32;
+        }
+        else if name=="filter"
+        {
+            self.filter=match value {
+                "none" => crtFilter::filterNone,
+                "scanlines" => crtFilter::filterScanlines,
+                "crt" => crtFilter::filterCrt,
+                _ => Self::abort(&format!("Unknown filter {}",value)),
+            };
+        }
+        else if name=="border" { self.showBorder=Self::parseBool(name,value); }
+        else if name=="joystick-a" { self.joystickA=Self::parseJoystick(name,value); }
+        else if name=="joystick-b" { self.joystickB=Self::parseJoystick(name,value); }
+        else if name=="printer" { self.printerFile=String::from(value); }
+        else if name=="printer-mode"
+        {
+            if (value!="raw") && (value!="text") { Self::abort(&format!("Unknown printer mode {}",value)); }
+            self.printerText=value=="text";
+        }
+        else if name=="record" { self.videoFile=String::from(value); }
+        else if name=="max-instructions" { self.maxInstructions=Self::parseNumber(name,value); }
+        else if name=="stop-at"
+        {
+            let stopAddr=match value.split_once(':') {
+                Some((segStr,offStr)) => (u16::from_str_radix(segStr,16),u16::from_str_radix(offStr,16)),
+                None => Self::abort(&format!("Invalid stop address {}, use cs:ip in hex",value)),
+            };
+            if let (Ok(stopCS),Ok(stopIP))=stopAddr
+            {
+                self.stopAddress=Some((stopCS,stopIP));
+            }
+            else
+            {
+                Self::abort(&format!("Invalid stop address {}, use cs:ip in hex",value));
+            }
+        }
+        else if name=="stop-at-hlt" { self.stopOnHlt=Self::parseBool(name,value); }
+        else if name=="stop-text" { self.stopText=String::from(value); }
+        else if name=="png" { self.pngFile=String::from(value); }
+        else if name=="text-dump" { self.textFile=String::from(value); }
+        else
+        {
+            Self::abort(&format!("Unknown option {}",name));
+        }
+    }
+
+    /*
+        profile file, the INI/TOML subset:
+            # comment (or ;)
+            [section]           sections are just for reading, names are global
+            name = value        value may be "quoted", flags take true/false
+    */
+    pub fn loadProfile(&mut self,fname:&str)
+    {
+        let contents=match fs::read_to_string(fname) {
+            Ok(c) => c,
+            Err(e) => Self::abort(&format!("Unable to open profile {} error:{}",fname,e)),
+        };
+
+        for (lineNum,rawLine) in contents.lines().enumerate()
+        {
+            let line=rawLine.trim();
+            if (line=="") || line.starts_with('#') || line.starts_with(';') || line.starts_with('[')
+            {
+                continue;
+            }
+
+            match line.split_once('=') {
+                Some((name,value)) => {
+                    let mut val=value.trim();
+                    if (val.len()>=2) && ((val.starts_with('"') && val.ends_with('"')) || (val.starts_with('\'') && val.ends_with('\'')))
+                    {
+                        val=&val[1..val.len()-1];
+                    }
+                    self.setOption(name.trim(),val);
+                },
+                None => Self::abort(&format!("{} line {}: expected name = value",fname,lineNum+1)),
+            }
+        }
+    }
+
+    // a bare disk image is a floppy up to 1.44Mb, a hard disk if bigger
+    fn setBareImage(&mut self,fname:&str)
+    {
+        let fileLen=match fs::metadata(fname) {
+            Ok(md) => md.len(),
+            Err(e) => Self::abort(&format!("Unable to open disk image {} error:{}",fname,e)),
+        };
+
+        if fileLen>1474560 { self.hdaImage=String::from(fname); }
+        else { self.fdaImage=String::from(fname); }
+    }
+
+    // the profile comes first, wherever --config is, so that the other options override it
+    pub fn fromArgs(args:&[String]) -> Self
+    {
+        let mut cfg=config::new();
+
+        let mut idx=0;
+        while idx<args.len()
+        {
+            if (args[idx]=="--config") && ((idx+1)<args.len())
+            {
+                cfg.loadProfile(&args[idx+1]);
+            }
+            idx+=1;
+        }
+
+        idx=0;
+        while idx<args.len()
+        {
+            let arg=&args[idx];
+            if (arg=="--help") || (arg=="-h")
+            {
+                println!("{}",SYNTAX);
+                process::exit(0x0);
+            }
+            else if let Some(optName)=arg.strip_prefix("--")
+            {
+                if Self::isFlagOption(optName)
+                {
+                    cfg.setOption(optName,"true");
+                }
+                else if (idx+1)<args.len()
+                {
+                    if optName!="config" { cfg.setOption(optName,&args[idx+1]); }
+                    idx+=1;
+                }
+                else
+                {
+                    Self::abort(&format!("Option {} needs a value\n\n{}",arg,SYNTAX));
+                }
+            }
+            else
+            {
+                cfg.setBareImage(arg);
+            }
+            idx+=1;
+        }
+
+        if cfg.headless && cfg.terminal
+        {
+            Self::abort("--headless and --terminal can't be used together");
+        }
+
+        if (cfg.getRunMode()==0) && (cfg.fdaImage=="") && (cfg.fdbImage=="") && (cfg.hdaImage=="") && (cfg.hdbImage=="")
+        {
+            Self::abort(&format!("Nothing to run, give a disk image or a program\n\n{}",SYNTAX));
+        }
+
+        return cfg;
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::env;
+    use std::process;
+    use std::process::Command;
+
+    // config errors end the process, so the bad arguments are parsed by this test binary run again
+    const CHILD_ARGS_VAR:&str="DREAM86_CONFIG_TEST_ARGS";
+
+    fn toArgs(args:&[&str]) -> Vec<String>
+    {
+        return args.iter().map(|a| String::from(*a)).collect();
+    }
+
+    // a file in the temp dir, unique to this test run
+    fn writeTempFile(name:&str,contents:&[u8]) -> String
+    {
+        let path=env::temp_dir().join(format!("dream86_{}_{}",process::id(),name));
+        fs::write(&path,contents).unwrap();
+        return path.to_string_lossy().into_owned();
+    }
+
+    // exit status and error message of parsing args in a child process
+    fn parseInChild(args:&[&str]) -> (i32,String)
+    {
+        let output=Command::new(env::current_exe().unwrap())
+            .args(["--exact","config::tests::parseChildArgs","--nocapture","--test-threads","1"])
+            .env(CHILD_ARGS_VAR,args.join("\n"))
+            .output()
+            .unwrap();
+        return (output.status.code().unwrap_or(-1),String::from_utf8_lossy(&output.stderr).into_owned());
+    }
+
+    #[test]
+    fn parseChildArgs()
+    {
+        if let Ok(childArgs)=env::var(CHILD_ARGS_VAR)
+        {
+            let args:Vec<&str>=childArgs.split('\n').collect();
+            config::fromArgs(&toArgs(&args));
+        }
+    }
+
+    #[test]
+    fn flagsNeedNoValue()
+    {
+        let cfg=config::fromArgs(&toArgs(&["--headless","--stop-at-hlt","--fda","a.img","--border"]));
+        assert!(cfg.headless);
+        assert!(cfg.stopOnHlt);
+        assert!(cfg.showBorder);
+        assert!(!cfg.debug);
+        assert_eq!(cfg.fdaImage,"a.img");
+    }
+
+    #[test]
+    fn optionsTakeTheNextArgument()
+    {
+        let cfg=config::fromArgs(&toArgs(&["--fda","a.img","--hda","c.img","--boot","c","--ram","256","--speed","1000",
+                                           "--machine","mda","--zoom","3","--filter","crt","--joystick-a","none",
+                                           "--stop-at","f000:0100","--printer-mode","text","--monitor","composite"]));
+        assert_eq!(cfg.hdaImage,"c.img");
+        assert_eq!(cfg.getBootDrive(),0x80);
+        assert_eq!(cfg.ramKb,256);
+        assert_eq!(cfg.speed,1000);
+        assert!(cfg.machType==machineType::machineMDA);
+        assert_eq!(cfg.zoom,3);
+        assert!(cfg.filter==crtFilter::filterCrt);
+        assert!(cfg.joystickA==joystickSource::joyNone);
+        assert_eq!(cfg.stopAddress,Some((0xf000,0x0100)));
+        assert!(cfg.printerText);
+        assert!(cfg.compositeMonitor);
+    }
+
+    #[test]
+    fn speedMaxAndProgramFiles()
+    {
+        let cfg=config::fromArgs(&toArgs(&["--speed","max","--com","prog.com"]));
+        assert_eq!(cfg.speed,0);
+        assert_eq!(cfg.getRunMode(),1);
+        assert_eq!(cfg.getProgramFile(),"prog.com");
+
+        let cfg=config::fromArgs(&toArgs(&["--bin","test.bin","--com","prog.com"]));
+        assert_eq!(cfg.getRunMode(),2);
+        assert_eq!(cfg.getProgramFile(),"test.bin");
+    }
+
+    #[test]
+    fn commandLineWinsOverProfile()
+    {
+        let profile=writeTempFile("precedence.ini",b"ram = 512\nmachine = cga\nfda = profile.img\n");
+        let cfg=config::fromArgs(&toArgs(&["--ram","256","--config",&profile]));
+        assert_eq!(cfg.ramKb,256);
+        assert!(cfg.machType==machineType::machineCGA);
+        assert_eq!(cfg.fdaImage,"profile.img");
+        fs::remove_file(&profile).ok();
+    }
+
+    #[test]
+    fn profileQuotesCommentsAndSections()
+    {
+        let profile=writeTempFile("syntax.ini",
+            b"# a comment\n; another one\n\n[drives]\nfda = \"my disk.img\"\n  hda='hard disk.img'  \n[display]\nborder = yes\nstop-text = \"A> \"\n");
+        let mut cfg=config::new();
+        cfg.loadProfile(&profile);
+        assert_eq!(cfg.fdaImage,"my disk.img");
+        assert_eq!(cfg.hdaImage,"hard disk.img");
+        assert!(cfg.showBorder);
+        // the quotes keep the trailing space
+        assert_eq!(cfg.stopText,"A> ");
+        fs::remove_file(&profile).ok();
+    }
+
+    #[test]
+    fn bareImageIsFloppyOrHardDiskBySize()
+    {
+        let floppy=writeTempFile("floppy.img",&vec![0u8;1474560]);
+        let hardDisk=writeTempFile("harddisk.img",&vec![0u8;1474561]);
+
+        let cfg=config::fromArgs(&toArgs(&[&floppy]));
+        assert_eq!(cfg.fdaImage,floppy);
+        assert_eq!(cfg.hdaImage,"");
+        assert_eq!(cfg.getBootDrive(),0);
+
+        let cfg=config::fromArgs(&toArgs(&[&hardDisk]));
+        assert_eq!(cfg.hdaImage,hardDisk);
+        assert_eq!(cfg.fdaImage,"");
+        assert_eq!(cfg.getBootDrive(),0x80);
+
+        fs::remove_file(&floppy).ok();
+        fs::remove_file(&hardDisk).ok();
+    }
+
+    #[test]
+    fn badValuesAreConfigErrors()
+    {
+        let badArgs:[&[&str];7]=[
+            &["--fda","a.img","--machine","pcjr"],
+            &["--fda","a.img","--ram","1024"],
+            &["--fda","a.img","--ram","lots"],
+            &["--fda","a.img","--speed","fast"],
+            &["--fda","a.img","--turbo","on"],
+            &["--fda","a.img","--ram"],
+            &["--fda","a.img","--headless","--terminal"],
+        ];
+        let messages=["Unknown machine type pcjr","64 to 640 KB","Invalid number lots for ram","Invalid number fast for speed",
+                      "Unknown option turbo","Option --ram needs a value","can't be used together"];
+
+        for (args,msg) in badArgs.iter().zip(messages)
+        {
+            let (status,stderr)=parseInChild(args);
+            assert_eq!(status,emuLog::EXIT_BAD_CONFIG,"{:?}",args);
+            assert!(stderr.contains(msg),"{:?}: {}",args,stderr);
+        }
+    }
+
+    #[test]
+    fn malformedProfileLineIsAConfigError()
+    {
+        let profile=writeTempFile("malformed.ini",b"[machine]\nram = 640\nmachine cga\n");
+        let (status,stderr)=parseInChild(&["--config",&profile,"--fda","a.img"]);
+        assert_eq!(status,emuLog::EXIT_BAD_CONFIG);
+        assert!(stderr.contains("line 3: expected name = value"),"{}",stderr);
+        fs::remove_file(&profile).ok();
+    }
+}
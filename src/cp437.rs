@@ -0,0 +1,38 @@
+/* codepage 437 to unicode - dream86 */
+
+const CP437HIGH: [char;128] =
+[
+    'Ç','ü','é','â','ä','à','å','ç','ê','ë','è','ï','î','ì','Ä','Å',
+    'É','æ','Æ','ô','ö','ò','û','ù','ÿ','Ö','Ü','¢','£','¥','₧','ƒ',
+    'á','í','ó','ú','ñ','Ñ','ª','º','¿','⌐','¬','½','¼','¡','«','»',
+    '░','▒','▓','│','┤','╡','╢','╖','╕','╣','║','╗','╝','╜','╛','┐',
+    '└','┴','┬','├','─','┼','╞','╟','╚','╔','╩','╦','╠','═','╬','╧',
+    '╨','╤','╥','╙','╘','╒','╓','╫','╪','┘','┌','█','▄','▌','▐','▀',
+    'α','ß','Γ','π','Σ','σ','µ','τ','Φ','Θ','Ω','δ','∞','φ','ε','∩',
+    '≡','±','≥','≤','⌠','⌡','÷','≈','°','∙','·','√','ⁿ','²','■','\u{a0}'
+];
+
+const CP437LOW: [char;32] =
+[
+    ' ','☺','☻','♥','♦','♣','♠','•','◘','○','◙','♂','♀','♪','♫','☼',
+    '►','◄','↕','‼','¶','§','▬','↨','↑','↓','→','←','∟','↔','▲','▼'
+];
+
+// maps a byte to its printable cp437 glyph (control codes become the PC graphic characters)
+pub fn cp437ToChar(b:u8) -> char
+{
+    if b<0x20
+    {
+        return CP437LOW[b as usize];
+    }
+    else if b==0x7f
+    {
+        return '⌂';
+    }
+    else if b>=0x80
+    {
+        return CP437HIGH[(b-0x80) as usize];
+    }
+
+    return b as char;
+}
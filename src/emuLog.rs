@@ -0,0 +1,36 @@
+/* messages of the emulator itself, kept apart from what the guest shows - dream86 */
+
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::termif;
+
+// exit status when the emulation can't go on: unknown opcode or interrupt, unreadable file
+pub const EXIT_ABORTED:i32=2;
+// exit status for invalid options, profile or disk images
+pub const EXIT_BAD_CONFIG:i32=3;
+
+// headless and terminal runs own stdout, the trace would get mixed with their output
+static TRACE_ENABLED:AtomicBool=AtomicBool::new(true);
+
+pub fn setTraceEnabled(enabled:bool)
+{
+    TRACE_ENABLED.store(enabled,Ordering::Relaxed);
+}
+
+// informative messages on what the guest does, like the video mode switches
+pub fn trace(msg:&str)
+{
+    if TRACE_ENABLED.load(Ordering::Relaxed)
+    {
+        println!("{}",msg);
+    }
+}
+
+// the terminal is given back first, or the message would be lost in the alternate screen
+pub fn fatalError(msg:&str,exitCode:i32) -> !
+{
+    termif::restoreTerminal();
+    eprintln!("{}",msg);
+    process::exit(exitCode);
+}
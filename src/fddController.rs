@@ -4,12 +4,12 @@
 use std::io::prelude::*;
 use std::fs::File;
 use std::io::SeekFrom;
-use std::process;
 use std::fs;
 
 
 use crate::vga::vga;
 use crate::machine::machine;
+use crate::emuLog;
 
 #[derive(PartialEq)]
 pub enum mediaType
@@ -18,39 +18,75 @@ pub enum mediaType
     floppy144
 }
 
-pub struct fddController
+struct diskImage
 {
-    fddFullPath: String,
+    driveNum: u8,
+    fullPath: String,
     diskType: mediaType
 }
 
+pub struct fddController
+{
+    // drives 0 (A:), 1 (B:), 80h (C:), 81h (D:)
+    drives: Vec<diskImage>
+}
+
 impl fddController
 {
-    pub fn new(diskImage:&String) -> Self 
+    pub fn new() -> Self 
     {
-        let mut mType=mediaType::floppy144;
-        let fileLen = fs::metadata(diskImage).unwrap().len();
-        if fileLen>1474560
+        fddController
         {
-            mType=mediaType::hardDisk;
+            drives: Vec::new()
         }
+    }
 
-        fddController
+    pub fn attachImage(&mut self,driveNum:u8,imageFile:&str)
+    {
+        if let Err(e)=fs::metadata(imageFile)
         {
-            fddFullPath: diskImage.clone(),
-            diskType: mType
+            emuLog::fatalError(&format!("Unable to open disk image {} error:{}",imageFile,e),emuLog::EXIT_BAD_CONFIG);
         }
+
+        let mType=if driveNum>=0x80 { mediaType::hardDisk } else { mediaType::floppy144 };
+        self.drives.push(diskImage
+        {
+            driveNum: driveNum,
+            fullPath: String::from(imageFile),
+            diskType: mType
+        });
     }
 
-    pub fn readDiskSectors(&self,pmachine:&mut machine,pvga:&mut vga,numOfSectorsToRead:u64,
+    fn getDrive(&self,driveNum:u8) -> Option<&diskImage>
+    {
+        return self.drives.iter().find(|d| d.driveNum==driveNum);
+    }
+
+    pub fn isDrivePresent(&self,driveNum:u8) -> bool
+    {
+        return self.getDrive(driveNum).is_some();
+    }
+
+    pub fn getNumberOfDrives(&self,hardDisks:bool) -> u8
+    {
+        return self.drives.iter().filter(|d| (d.diskType==mediaType::hardDisk)==hardDisks).count() as u8;
+    }
+
+    // false if there's no image for the drive
+    pub fn readDiskSectors(&self,pmachine:&mut machine,pvga:&mut vga,driveNum:u8,numOfSectorsToRead:u64,
                            sectorNumber:u64,cylinderNumber:u64,_headNumber:u64,
-                           loAddr:u16,hiAddr:u16)
+                           loAddr:u16,hiAddr:u16) -> bool
     {
+        let drive=match self.getDrive(driveNum) {
+            Some(d) => d,
+            None => return false
+        };
+
         let bytesPerSector=512;
         let sectorsPerTrack;
         let headsPerCylinder;
 
-        if self.diskType==mediaType::floppy144
+        if drive.diskType==mediaType::floppy144
         {
             sectorsPerTrack=18;
             headsPerCylinder=2;
@@ -66,11 +102,10 @@ impl fddController
         let lba:u64=(((cylinderNumber*headsPerCylinder)+_headNumber)*sectorsPerTrack)+(sectorNumber);
         let imgOffset=lba*bytesPerSector;
 
-        let mut f = match File::open(self.fddFullPath.clone()) {
+        let mut f = match File::open(drive.fullPath.clone()) {
             Ok(f) => f,
             Err(_e) => {
-                println!("Unable to open file {}",self.fddFullPath);
-                process::exit(0x100);
+                emuLog::fatalError(&format!("Unable to open file {}",drive.fullPath),emuLog::EXIT_ABORTED);
             }
         };
         f.seek(SeekFrom::Start(imgOffset)).ok();
@@ -83,5 +118,7 @@ impl fddController
             pmachine.writeMemory(hiAddr,memOffs,buf[0],pvga);
             memOffs+=1;
         }
+
+        return true;
     }
 }
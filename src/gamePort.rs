@@ -0,0 +1,131 @@
+/* game port (201h) joystick emulation - dream86 */
+
+/*
+    |7|6|5|4|3|2|1|0|  201 Game Port
+    | | | | | | | `---- joystick A, X axis one-shot (1 = timing)
+    | | | | | | `----- joystick A, Y axis one-shot
+    | | | | | `------ joystick B, X axis one-shot
+    | | | | `------- joystick B, Y axis one-shot
+    | | | `-------- joystick A, button 1 (0 = pressed)
+    | | `--------- joystick A, button 2
+    | `---------- joystick B, button 1
+    `----------- joystick B, button 2
+
+    a write to 201h fires the four one-shots, each one stays high
+    for a time proportional to the axis potentiometer resistance
+*/
+
+// one-shot length, in emulated instructions, is BASE+(axis value*SCALE)
+const ONESHOT_BASE:u32=8;
+const ONESHOT_SCALE:u32=4;
+
+pub struct gamePort
+{
+    pub connected: [bool;2],
+    pub axes: [u8;4],
+    pub buttons: [bool;4],
+    timers: [u32;4]
+}
+
+impl gamePort
+{
+    pub fn new() -> Self
+    {
+        gamePort
+        {
+            connected: [false,false],
+            axes: [0x80,0x80,0x80,0x80],
+            buttons: [false,false,false,false],
+            timers: [0,0,0,0]
+        }
+    }
+
+    // joyNum is 0 for joystick A, 1 for joystick B
+    pub fn setJoystick(&mut self,joyNum:usize,xpos:u8,ypos:u8,button1:bool,button2:bool)
+    {
+        self.connected[joyNum]=true;
+        self.axes[joyNum*2]=xpos;
+        self.axes[(joyNum*2)+1]=ypos;
+        self.buttons[joyNum*2]=button1;
+        self.buttons[(joyNum*2)+1]=button2;
+    }
+
+    pub fn disconnectJoystick(&mut self,joyNum:usize)
+    {
+        self.connected[joyNum]=false;
+        self.buttons[joyNum*2]=false;
+        self.buttons[(joyNum*2)+1]=false;
+    }
+
+    pub fn isAnyConnected(&self) -> bool
+    {
+        return self.connected[0] || self.connected[1];
+    }
+
+    pub fn write0x201(&mut self)
+    {
+        for axis in 0..4
+        {
+            if self.connected[axis/2]
+            {
+                self.timers[axis]=ONESHOT_BASE+((self.axes[axis] as u32)*ONESHOT_SCALE);
+            }
+        }
+    }
+
+    pub fn read0x201(&self) -> u8
+    {
+        let mut retval:u8=0;
+
+        for axis in 0..4
+        {
+            // a missing stick never ends its one-shot
+            if (!self.connected[axis/2]) || (self.timers[axis]>0)
+            {
+                retval|=1<<axis;
+            }
+        }
+
+        retval|=self.getButtonBits();
+        return retval;
+    }
+
+    // bits 4-7 as read from the port
+    pub fn getButtonBits(&self) -> u8
+    {
+        let mut retval:u8=0;
+        for btn in 0..4
+        {
+            if !self.buttons[btn]
+            {
+                retval|=0x10<<btn;
+            }
+        }
+        return retval;
+    }
+
+    // INT 15,84 DX=1 - joystick positions, zero for a missing stick
+    pub fn getBiosPositions(&self) -> [u16;4]
+    {
+        let mut retval:[u16;4]=[0,0,0,0];
+        for axis in 0..4
+        {
+            if self.connected[axis/2]
+            {
+                retval[axis]=self.axes[axis] as u16;
+            }
+        }
+        return retval;
+    }
+
+    pub fn update(&mut self)
+    {
+        for axis in 0..4
+        {
+            if self.timers[axis]>0
+            {
+                self.timers[axis]-=1;
+            }
+        }
+    }
+}
@@ -3,16 +3,20 @@
 use std::time::Instant;
 use std::collections::HashMap;
 use std::io::{stdout, Write};
+use std::path::Path;
 use crossterm::{ExecutableCommand, QueueableCommand,terminal, cursor, style::{self, Stylize}};
 use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyModifiers};
 
 extern crate minifb;
-use minifb::{Key,  KeyRepeat, Scale, Window, WindowOptions};
+use minifb::{Key,  KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
 
 use crate::machine::machine;
+use crate::machine::machineType;
 use crate::fddController::fddController;
 use crate::x86cpu::x86cpu;
 use crate::vga::vga;
+use crate::videoFrame::videoFrame;
+use crate::videoScaler::videoScaler;
 
 #[derive(PartialEq)]
 pub enum keyAction 
@@ -28,6 +32,14 @@ pub enum keyAction
     actionDecDebugCursor,
 }
 
+#[derive(PartialEq,Clone,Copy)]
+pub enum joystickSource
+{
+    joyNone,
+    joyKeys,
+    joyMouse,
+}
+
 pub struct guiif
 {
     pub dbgcs: u16,
@@ -36,29 +48,32 @@ pub struct guiif
     pub dbgInstrLine: u16,
     pub dbgRegline: u16,
     pub dbgMemoryLine: u16,
-    pub frameBuffer: Vec<u32>,
+    pub frame: videoFrame,
+    pub scaler: videoScaler,
     pub videoWindow: Window,
     pub videoWinWidth: u32,
     pub videoWinHeight: u32,
-    pub videoMode: u8
+    pub joystickA: joystickSource,
+    pub joystickB: joystickSource
 }
 
 impl guiif
 {
-    pub fn new(videomode:u8,inCS:u16,inIP:u16) -> Self 
+    pub fn new(pvga:&vga,scaler:videoScaler,inCS:u16,inIP:u16) -> Self 
     {
         let mut stdout = stdout();
         stdout.execute(terminal::Clear(terminal::ClearType::All)).ok();
 
-        let vwidth:u32=320;
-        let vheight:u32=200;
+        // one resizable window for every video mode, the picture is scaled and letterboxed into it
+        let (vwidth,vheight)=scaler.getWindowSize();
         let window:Window=Window::new("dream86",vwidth as usize,vheight as usize,WindowOptions {
-            scale: Scale::X2,
+            resize: true,
             ..WindowOptions::default()
         }).unwrap_or_else(|e| { panic!("{}", e); });
-        let buffer:Vec<u32>=Vec::new();
+        let mut frame=videoFrame::new();
+        frame.fitVideomode(pvga);
 
-        let mut newGUI=guiif 
+        let newGUI=guiif 
         {
             dbgcs: inCS, 
             dbgip: inIP, 
@@ -66,87 +81,151 @@ impl guiif
             dbgInstrLine: 9, 
             dbgRegline: 1,
             dbgMemoryLine: 30,
-            frameBuffer: buffer,
+            frame: frame,
+            scaler: scaler,
             videoWindow: window,
             videoWinWidth: vwidth,
             videoWinHeight: vheight,
-            videoMode: videomode
+            joystickA: joystickSource::joyKeys,
+            joystickB: joystickSource::joyMouse
         };
 
-        newGUI.initVideomode(videomode);
         return newGUI;
     }    
 
-    fn initVideomode(&mut self,videomode:u8)
+    // the picture follows the window size, whatever the video mode
+    pub fn updateVideoWindow(&mut self)
     {
-        let mut vwidth:u32=0;
-        let mut vheight:u32=0;
-        let mut window:Window;
-        let mut buffer:Vec<u32>;
-
-        if videomode==0x13 { vwidth=320; vheight=200; }
-        else if videomode==0x00 { vwidth=360; vheight=400; }
-        else if videomode==0x01 { vwidth=360; vheight=400; }
-        else if videomode==0x02 { vwidth=720; vheight=400; }
-        else if videomode==0x03 { vwidth=720; vheight=400; }
-        else if videomode==0x04 { vwidth=320; vheight=200; }
-        else if videomode==0x05 { vwidth=320; vheight=200; }
-        else if videomode==0x06 { vwidth=640; vheight=200; }
-        else if videomode==0x0d { vwidth=320; vheight=200; }
-        else if videomode==0x10 { vwidth=640; vheight=350; }
-
-        if videomode==0x13 || videomode==0x04  || videomode==0x05 || videomode==0x0d || videomode==0x06
+        let (winWidth,winHeight)=self.videoWindow.get_size();
+        if (winWidth>0) && (winHeight>0)
         {
-            window=Window::new("dream86",vwidth as usize,vheight as usize,WindowOptions {
-                scale: Scale::X2,
-                ..WindowOptions::default()
-            }).unwrap_or_else(|e| { panic!("{}", e); });    
+            self.videoWinWidth=winWidth as u32;
+            self.videoWinHeight=winHeight as u32;
         }
-        else
+
+        self.scaler.scaleFrame(&self.frame,self.videoWinWidth,self.videoWinHeight);
+        self.videoWindow.update_with_buffer(&self.scaler.pixels,self.videoWinWidth as usize,self.videoWinHeight as usize).unwrap();
+    }
+
+    pub fn checkExit(&mut self) -> bool
+    {
+        return self.videoWindow.is_key_down(Key::Tab);
+    }
+
+    fn readJoystick(&self,source:joystickSource) -> Option<(u8,u8,bool,bool)>
+    {
+        if source==joystickSource::joyKeys
         {
-            window=Window::new("dream86",vwidth as usize,vheight as usize,WindowOptions {
-                /*scale: Scale::X2,*/
-                ..WindowOptions::default()
-            }).unwrap_or_else(|e| { panic!("{}", e); });    
-        }
+            // numeric keypad moves the stick, 0 and . are the buttons
+            let mut xpos:u8=0x80;
+            let mut ypos:u8=0x80;
+            let w=&self.videoWindow;
 
-        buffer=Vec::with_capacity((vwidth as usize)*(vheight as usize));
-        for _i in 0..(vwidth as usize)*(vheight as usize)
+            if w.is_key_down(Key::NumPad4) || w.is_key_down(Key::NumPad7) || w.is_key_down(Key::NumPad1) { xpos=0; }
+            if w.is_key_down(Key::NumPad6) || w.is_key_down(Key::NumPad9) || w.is_key_down(Key::NumPad3) { xpos=0xff; }
+            if w.is_key_down(Key::NumPad8) || w.is_key_down(Key::NumPad7) || w.is_key_down(Key::NumPad9) { ypos=0; }
+            if w.is_key_down(Key::NumPad2) || w.is_key_down(Key::NumPad1) || w.is_key_down(Key::NumPad3) { ypos=0xff; }
+
+            return Some((xpos,ypos,w.is_key_down(Key::NumPad0),w.is_key_down(Key::NumPadDot)));
+        }
+        else if source==joystickSource::joyMouse
         {
-            buffer.push(0);
+            // mouse position inside the picture is the stick position
+            let mut xpos:u8=0x80;
+            let mut ypos:u8=0x80;
+            let (picx,picy,picWidth,picHeight)=self.scaler.getPictureRect(self.frame.width,self.frame.height,self.videoWinWidth,self.videoWinHeight);
+            if let Some((mx,my))=self.videoWindow.get_mouse_pos(MouseMode::Clamp)
+            {
+                if (picWidth>0) && (picHeight>0)
+                {
+                    xpos=(((mx-(picx as f32))*255.0)/(picWidth as f32)).clamp(0.0,255.0) as u8;
+                    ypos=(((my-(picy as f32))*255.0)/(picHeight as f32)).clamp(0.0,255.0) as u8;
+                }
+            }
+
+            return Some((xpos,ypos,
+                self.videoWindow.get_mouse_down(MouseButton::Left),
+                self.videoWindow.get_mouse_down(MouseButton::Right)));
         }
-        window.update_with_buffer(&buffer,vwidth as usize,vheight as usize).unwrap();
 
-        self.videoWinWidth=vwidth;
-        self.videoWinHeight=vheight;
-        self.frameBuffer=buffer;
-        self.videoWindow=window;
-        self.videoMode=videomode;
+        return None;
     }
 
-    pub fn updateVideoWindow(&mut self,pvga:&vga)
+    pub fn updateJoysticks(&self,pmachine:&mut machine)
     {
-        // check if videomode changed
-        if (*pvga).mode!=self.videoMode.into()
+        let sources=[self.joystickA,self.joystickB];
+        for joyNum in 0..2
         {
-            self.initVideomode((*pvga).mode as u8);
+            match self.readJoystick(sources[joyNum])
+            {
+                Some((xpos,ypos,b1,b2)) => pmachine.gameport.setJoystick(joyNum,xpos,ypos,b1,b2),
+                None => pmachine.gameport.disconnectJoystick(joyNum)
+            }
         }
+    }
 
-        self.videoWindow.update_with_buffer(&self.frameBuffer,self.videoWinWidth as usize,self.videoWinHeight as usize).unwrap();
+    // first dream86_NNNN.ext not already in the current directory
+    fn getCaptureFilename(ext:&str) -> String
+    {
+        let mut captureNum=0;
+        loop
+        {
+            let fname=format!("dream86_{:04}.{}",captureNum,ext);
+            if !Path::new(&fname).exists()
+            {
+                return fname;
+            }
+            captureNum+=1;
+        }
     }
 
-    pub fn checkExit(&mut self) -> bool
+    // emulator hotkeys, pressed together with right ctrl (they don't reach the emulated machine)
+    fn processHotkeys(&mut self,pvga:&mut vga)
     {
-        return self.videoWindow.is_key_down(Key::Tab);
+        self.videoWindow.get_keys_pressed(KeyRepeat::No).iter().for_each(|key| {
+            match key {
+                Key::C => {
+                    // only the CGA has a composite output
+                    if pvga.cardType==machineType::machineCGA { pvga.compositeOutput=!pvga.compositeOutput; }
+                },
+                Key::P => {
+                    let fname=guiif::getCaptureFilename("png");
+                    pvga.saveScreenshot(&fname);
+                },
+                Key::T => {
+                    let fname=guiif::getCaptureFilename("txt");
+                    pvga.saveTextScreen(&fname,true);
+                },
+                Key::A => self.scaler.nextMode(),
+                Key::F => self.scaler.nextFilter(),
+                Key::B => self.scaler.showBorder=!self.scaler.showBorder,
+                Key::V => {
+                    if pvga.recorder.isRecording()
+                    {
+                        pvga.stopRecording();
+                    }
+                    else
+                    {
+                        let fname=guiif::getCaptureFilename("y4m");
+                        pvga.startRecording(&fname);
+                    }
+                },
+                _ => return ,
+            }
+        });
     }
 
-    pub fn processKeys(&mut self,pmachine:&mut machine,theCPU:&mut x86cpu,_pvga:&mut vga) -> bool
+    pub fn processKeys(&mut self,pmachine:&mut machine,theCPU:&mut x86cpu,pvga:&mut vga) -> bool
     {
         let mut kpress=false;
 
-        if self.checkLShiftPressed()
+        self.updateJoysticks(pmachine);
+        pmachine.setShiftKeys(self.getShiftKeys());
+
+        if self.videoWindow.is_key_down(Key::RightCtrl)
         {
-            pmachine.addKeystroke(0xff);
+            self.processHotkeys(pvga);
+            return kpress;
         }
 
         self.videoWindow.get_keys_pressed(KeyRepeat::No).iter().for_each(|key| {
@@ -226,9 +305,15 @@ impl guiif
         return kpress;
     }
 
-    pub fn checkLShiftPressed(&mut self) -> bool
+    // shift keys as in 40:17, right ctrl is left out as it's for the hotkeys
+    pub fn getShiftKeys(&self) -> u8
     {
-        return self.videoWindow.is_key_down(Key::LeftShift);
+        let mut keys:u8=0;
+        if self.videoWindow.is_key_down(Key::RightShift) { keys|=0x01; }
+        if self.videoWindow.is_key_down(Key::LeftShift) { keys|=0x02; }
+        if self.videoWindow.is_key_down(Key::LeftCtrl) { keys|=0x04; }
+        if self.videoWindow.is_key_down(Key::LeftAlt) || self.videoWindow.is_key_down(Key::RightAlt) { keys|=0x08; }
+        return keys;
     }
 
     pub fn getKeyAction(&self) -> keyAction
@@ -0,0 +1,138 @@
+/* headless run, with no window and no terminal ui - dream86 */
+
+use crate::machine::machine;
+use crate::vga::vga;
+use crate::x86cpu::x86cpu;
+use crate::fddController::fddController;
+
+// looking for text on screen every instruction would be slow
+const TEXT_CHECK_INTERVAL:u64=10000;
+
+pub struct headless
+{
+    pub maxInstructions: u64,
+    pub stopAddress: Option<(u16,u16)>,
+    pub stopOnHlt: bool,
+    pub stopText: String,
+    pub pngFile: String,
+    pub textFile: String
+}
+
+impl headless
+{
+    pub fn new() -> Self
+    {
+        headless
+        {
+            maxInstructions: 0,
+            stopAddress: None,
+            stopOnHlt: false,
+            stopText: String::from(""),
+            pngFile: String::from(""),
+            textFile: String::from("")
+        }
+    }
+
+    fn screenContains(&self,pvga:&vga) -> bool
+    {
+        for rowText in pvga.getScreenText()
+        {
+            if rowText.contains(&self.stopText) { return true; }
+        }
+        return false;
+    }
+
+    // runs the machine until a stop condition, then dumps its final state
+    // the returned exit code is 1 if the instruction limit ran out while waiting for another condition, else 0
+    pub fn run(&self,pcpu:&mut x86cpu,pmachine:&mut machine,pvga:&mut vga,pdisk:&fddController) -> i32
+    {
+        let waitsForCondition=self.stopAddress.is_some() || self.stopOnHlt || (self.stopText!="");
+        let mut bytesRead=1;
+        let mut steps:u64=0;
+        let stopReason;
+        let mut exitCode=0;
+
+        loop
+        {
+            if let Some((stopCS,stopIP))=self.stopAddress
+            {
+                if (pcpu.cs==stopCS) && (pcpu.ip==stopIP)
+                {
+                    stopReason=format!("reached {:04x}:{:04x}",stopCS,stopIP);
+                    break;
+                }
+            }
+
+            pcpu.executeOne(pmachine,pvga,pdisk,false,&mut bytesRead,&0,&0);
+            pmachine.update(pcpu);
+            pvga.update();
+            steps+=1;
+
+            if self.stopOnHlt && pcpu.isHalted
+            {
+                stopReason=format!("HLT at {:04x}:{:04x}",pcpu.cs,pcpu.ip.wrapping_sub(1));
+                break;
+            }
+
+            if (self.stopText!="") && ((steps%TEXT_CHECK_INTERVAL)==0) && self.screenContains(pvga)
+            {
+                stopReason=format!("\"{}\" on screen",self.stopText);
+                break;
+            }
+
+            // a halted cpu doesn't execute instructions, so the limit counts the steps
+            if (self.maxInstructions>0) && (steps>=self.maxInstructions)
+            {
+                stopReason=format!("{} instructions limit",self.maxInstructions);
+                if waitsForCondition { exitCode=1; }
+                break;
+            }
+        }
+
+        self.dumpState(&stopReason,pcpu,pvga);
+        return exitCode;
+    }
+
+    fn dumpState(&self,stopReason:&str,pcpu:&x86cpu,pvga:&mut vga)
+    {
+        println!("dream86 stopped: {}, {} instructions executed",stopReason,pcpu.totInstructions);
+
+        let screenRows=pvga.getScreenText();
+        if screenRows.len()>0
+        {
+            println!("--- screen (mode {:02x}) ---",pvga.mode);
+            for rowText in screenRows
+            {
+                println!("{}",rowText.trim_end());
+            }
+            println!("---");
+        }
+        else
+        {
+            println!("--- screen is in graphics mode {:02x} ---",pvga.mode);
+        }
+
+        println!("AX={:04x} BX={:04x} CX={:04x} DX={:04x} SI={:04x} DI={:04x} BP={:04x} SP={:04x}",
+            pcpu.ax,pcpu.bx,pcpu.cx,pcpu.dx,pcpu.si,pcpu.di,pcpu.bp,pcpu.sp);
+        println!("CS={:04x} IP={:04x} DS={:04x} ES={:04x} SS={:04x} FLAGS={:04x}",
+            pcpu.cs,pcpu.ip,pcpu.ds,pcpu.es,pcpu.ss,pcpu.flags);
+
+        if self.pngFile!=""
+        {
+            if pvga.saveScreenshot(&self.pngFile)
+            {
+                println!("screen saved to {}",self.pngFile);
+            }
+        }
+
+        if self.textFile!=""
+        {
+            if pvga.saveTextScreen(&self.textFile,true)
+            {
+                println!("text screen saved to {}",self.textFile);
+            }
+        }
+
+        pvga.stopRecording();
+    }
+}
@@ -4,17 +4,32 @@ use chrono::Datelike;
 use std::fs::File;
 use std::io::prelude::*;
 use rand::Rng;
-use std::process;
 
 use crate::vga::vga;
 use crate::x86cpu::x86cpu;
 use crate::fddController::fddController;
-
-#[derive(PartialEq)]
+use crate::parallelPort::parallelPort;
+use crate::gamePort::gamePort;
+use crate::emuLog;
+
+// VBE tables in the video BIOS ROM area (c000:xxxx)
+const VESA_OEMSTRING_OFFS:u16=0x0000;
+const VESA_MODELIST_OFFS:u16=0x0020;
+const VESA_WINFUNC_OFFS:u16=0x0030;
+
+// ROM fonts returned by INT 10h,1130h, in the video BIOS segment too
+const VIDEO_FONT8X14_OFFS:u16=0x1000;
+const VIDEO_FONT8X8_OFFS:u16=0x2000;
+const VIDEO_FONT8X16_OFFS:u16=0x2800;
+const VIDEO_FONTALT_OFFS:u16=0x3800;
+
+#[derive(PartialEq,Clone,Copy)]
 pub enum machineType 
 {
     machineCGA,
     machineEGA,
+    machineMDA,
+    machineHercules,
 }
 
 pub struct machine 
@@ -26,7 +41,13 @@ pub struct machine
     pub keyboardQueue: Vec<u16>,
     pub lastScancode: u16,
     pub ppi_a: u8,
-    pub machType: machineType
+    pub machType: machineType,
+    pub conventionalKb: u16,
+    // false for com and bin programs, there's no BIOS code then
+    pub biosLoaded: bool,
+    bdaInitDone: bool,
+    pub lpt1: parallelPort,
+    pub gameport: gamePort
 }
 
 impl machine 
@@ -39,8 +60,7 @@ impl machine
         let mut f = match File::open(fname) {
             Ok(f) => f,
             Err(_e) => {
-                println!("Unable to open file {}",fname);
-                process::exit(0x0100);
+                emuLog::fatalError(&format!("Unable to open file {}",fname),emuLog::EXIT_ABORTED);
             }
         };
         let biosLen:usize=f.metadata().unwrap().len() as usize;
@@ -63,8 +83,7 @@ impl machine
         let mut f = match File::open(fname) {
             Ok(f) => f,
             Err(e) => {
-                println!("Unable to open file {} error:{}",fname,e);
-                process::exit(0x0100);
+                emuLog::fatalError(&format!("Unable to open file {} error:{}",fname,e),emuLog::EXIT_ABORTED);
             }
         };
         let comLen:usize=f.metadata().unwrap().len() as usize;
@@ -82,8 +101,7 @@ impl machine
         let mut f = match File::open(fname) {
             Ok(f) => f,
             Err(e) => {
-                println!("Unable to open file {} error:{}",fname,e);
-                process::exit(0x0100);
+                emuLog::fatalError(&format!("Unable to open file {} error:{}",fname,e),emuLog::EXIT_ABORTED);
             }
         };
         let comLen:usize=f.metadata().unwrap().len() as usize;
@@ -106,7 +124,12 @@ impl machine
         if addr16==0x03C6
         {
             // VGA palette mask
-            // TODO
+            pvga.write0x3c6(val);
+        }
+        else if addr16==0x03C7
+        {
+            // set palette color index to read for VGA
+            pvga.write0x3c7(val);
         }
         else if addr16==0x03C8
         {
@@ -118,6 +141,11 @@ impl machine
             // write r-g-b for VGA palette
             pvga.write0x3c9(val);
         }
+        else if addr16==0x03C0
+        {
+            // VGA attribute controller index/data
+            pvga.write0x3c0(val);
+        }
         else if addr16==0x03C4
         {
             // EGA sequencer registers select
@@ -138,10 +166,28 @@ impl machine
             // EGA graphics controller registers
             pvga.write0x3cf(val);
         }
+        else if addr16==0x03b8
+        {
+            // MDA/Hercules mode control
+            pvga.write0x3b8(val);
+            self.updateVideoBDA(pvga);
+        }
+        else if addr16==0x03bf
+        {
+            // Hercules configuration switch
+            pvga.write0x3bf(val);
+        }
+        else if addr16==0x03d8
+        {
+            // CGA mode control
+            pvga.write0x3d8(val);
+            self.updateVideoBDA(pvga);
+        }
         else if addr16==0x03d9
         {
             // palette select for cga
             pvga.write0x3d9(val);
+            self.updateVideoBDA(pvga);
         }
         else if (addr16==0x03b4) || (addr16==0x03d4)
         {
@@ -153,6 +199,21 @@ impl machine
             // MDA CRT data register	 (EGA/VGA)
             pvga.write0x3b5(val);            
         }
+        else if addr16==0x0378
+        {
+            // LPT1 data
+            self.lpt1.writeData(val);
+        }
+        else if addr16==0x037a
+        {
+            // LPT1 control
+            self.lpt1.writeControl(val);
+        }
+        else if addr16==0x0201
+        {
+            // game port, fire the joystick one-shots
+            self.gameport.write0x201();
+        }
         else if (addr8==0x61) || (addr16==0x61)
         {
             if ((self.ppi_a & 0x80) != 0) && ((val & 0x80) == 0)
@@ -213,17 +274,72 @@ impl machine
             let num:u16=pvga.read0x3b5() as u16;
             pcpu.ax=(pcpu.ax&0xff00)|num;
         }
+        else if addr16==0x3c0
+        {
+            pcpu.ax=(pcpu.ax&0xff00)|(pvga.read0x3c0() as u16);
+        }
+        else if addr16==0x3c1
+        {
+            // VGA attribute controller data
+            pcpu.ax=(pcpu.ax&0xff00)|(pvga.read0x3c1() as u16);
+        }
+        else if addr16==0x3c6
+        {
+            // VGA palette mask
+            pcpu.ax=(pcpu.ax&0xff00)|(pvga.read0x3c6() as u16);
+        }
+        else if addr16==0x3c7
+        {
+            // VGA DAC state
+            pcpu.ax=(pcpu.ax&0xff00)|(pvga.read0x3c7() as u16);
+        }
+        else if addr16==0x3c8
+        {
+            // VGA palette write index
+            pcpu.ax=(pcpu.ax&0xff00)|(pvga.read0x3c8() as u16);
+        }
+        else if addr16==0x3c9
+        {
+            // VGA palette r-g-b read
+            pcpu.ax=(pcpu.ax&0xff00)|(pvga.read0x3c9() as u16);
+        }
+        else if addr16==0x3c5
+        {
+            // VGA sequencer data
+            pcpu.ax=(pcpu.ax&0xff00)|(pvga.read0x3c5() as u16);
+        }
+        else if addr16==0x3cf
+        {
+            // VGA graphics controller data
+            pcpu.ax=(pcpu.ax&0xff00)|(pvga.read0x3cf() as u16);
+        }
+        else if addr16==0x3ba
+        {
+            // MDA/Hercules status
+            pcpu.ax=(pcpu.ax&0xff00)|(pvga.read0x3ba() as u16);
+        }
         else if addr16==0x3da
         {
             //let num:u16 = /*pvga.read0x3da() as u16;*/rand::thread_rng().gen_range(0..256);
             let num:u16 = pvga.read0x3da() as u16;
             pcpu.ax=(pcpu.ax&0xff00)|num;
         }
+        else if addr16==0x378
+        {
+            pcpu.ax=(pcpu.ax&0xff00)|(self.lpt1.dataReg as u16);
+        }
+        else if addr16==0x379
+        {
+            pcpu.ax=(pcpu.ax&0xff00)|(self.lpt1.readStatus() as u16);
+        }
+        else if addr16==0x37a
+        {
+            pcpu.ax=(pcpu.ax&0xff00)|(self.lpt1.readControl() as u16);
+        }
         else if addr16==0x201
         {
             // joystick read position&status
-            // TODO
-            pcpu.ax=(pcpu.ax&0xff00)|0xff;
+            pcpu.ax=(pcpu.ax&0xff00)|(self.gameport.read0x201() as u16);
         }
     }
 
@@ -270,8 +386,495 @@ impl machine
         return monthDayInHex;
     }
 
+    // INT 10,10 - palette registers, overscan and DAC
+    fn handlePaletteINT(&mut self,pcpu:&mut x86cpu,pvga:&mut vga)
+    {
+        let subFunction=pcpu.ax&0xff;
+        let bl=(pcpu.bx&0xff) as u8;
+        let bh=(pcpu.bx>>8) as u8;
+
+        if subFunction==0x00
+        {
+            // INT 10,1000 - set palette register BL to BH
+            pvga.setPaletteRegister(bl,bh);
+        }
+        else if subFunction==0x01
+        {
+            // INT 10,1001 - set overscan (border) color to BH
+            pvga.setPaletteRegister(0x11,bh);
+        }
+        else if subFunction==0x02
+        {
+            // INT 10,1002 - set all palette registers and overscan from the 17 bytes at ES:DX
+            for idx in 0..17
+            {
+                let val=self.readMemory(pcpu.es,pcpu.dx.wrapping_add(idx),pvga);
+                pvga.setPaletteRegister(if idx==16 { 0x11 } else { idx as u8 },val);
+            }
+        }
+        else if subFunction==0x03
+        {
+            // INT 10,1003 - Toggle Blink/Intensity Bit
+            // BL = 0 enable intensity, 1 enable blinking
+            pvga.setBlinkEnabled(bl!=0);
+        }
+        else if subFunction==0x07
+        {
+            // INT 10,1007 - read palette register BL into BH
+            pcpu.bx=(pcpu.bx&0x00ff)|((pvga.getPaletteRegister(bl) as u16)<<8);
+        }
+        else if subFunction==0x08
+        {
+            // INT 10,1008 - read overscan color into BH
+            pcpu.bx=(pcpu.bx&0x00ff)|((pvga.getPaletteRegister(0x11) as u16)<<8);
+        }
+        else if subFunction==0x09
+        {
+            // INT 10,1009 - read all palette registers and overscan to the 17 bytes at ES:DX
+            for idx in 0..17
+            {
+                let val=pvga.getPaletteRegister(if idx==16 { 0x11 } else { idx as u8 });
+                self.writeMemory(pcpu.es,pcpu.dx.wrapping_add(idx),val,pvga);
+            }
+        }
+        else if subFunction==0x10
+        {
+            // INT 10,1010 - set DAC register BX to DH red, CH green, CL blue
+            pvga.setDacEntry(bl,(pcpu.dx>>8) as u8,(pcpu.cx>>8) as u8,(pcpu.cx&0xff) as u8);
+        }
+        else if subFunction==0x12
+        {
+            // INT 10,1012 - set CX DAC registers from BX, with the red-green-blue triplets at ES:DX
+            for idx in 0..pcpu.cx
+            {
+                let tripletOffs=pcpu.dx.wrapping_add(idx.wrapping_mul(3));
+                let red=self.readMemory(pcpu.es,tripletOffs,pvga);
+                let green=self.readMemory(pcpu.es,tripletOffs.wrapping_add(1),pvga);
+                let blue=self.readMemory(pcpu.es,tripletOffs.wrapping_add(2),pvga);
+                pvga.setDacEntry((pcpu.bx.wrapping_add(idx)&0xff) as u8,red,green,blue);
+            }
+        }
+        else if subFunction==0x13
+        {
+            // INT 10,1013 - BL = 0: BH = paging mode (0 4 pages of 64 colors, 1 16 pages of 16); BL = 1: BH = page
+            if bl==0 { pvga.setColorPagingMode(bh!=0); }
+            else if bl==1 { pvga.setColorPage(bh); }
+        }
+        else if subFunction==0x15
+        {
+            // INT 10,1015 - read DAC register BX into DH red, CH green, CL blue
+            let (red,green,blue)=pvga.getDacEntry(bl);
+            pcpu.dx=(pcpu.dx&0x00ff)|((red as u16)<<8);
+            pcpu.cx=((green as u16)<<8)|(blue as u16);
+        }
+        else if subFunction==0x17
+        {
+            // INT 10,1017 - read CX DAC registers from BX, as red-green-blue triplets to ES:DX
+            for idx in 0..pcpu.cx
+            {
+                let tripletOffs=pcpu.dx.wrapping_add(idx.wrapping_mul(3));
+                let (red,green,blue)=pvga.getDacEntry((pcpu.bx.wrapping_add(idx)&0xff) as u8);
+                self.writeMemory(pcpu.es,tripletOffs,red,pvga);
+                self.writeMemory(pcpu.es,tripletOffs.wrapping_add(1),green,pvga);
+                self.writeMemory(pcpu.es,tripletOffs.wrapping_add(2),blue,pvga);
+            }
+        }
+        else if subFunction==0x18
+        {
+            // INT 10,1018 - set pixel mask to BL
+            pvga.write0x3c6(bl);
+        }
+        else if subFunction==0x19
+        {
+            // INT 10,1019 - read pixel mask into BL
+            pcpu.bx=(pcpu.bx&0xff00)|(pvga.read0x3c6() as u16);
+        }
+        else if subFunction==0x1a
+        {
+            // INT 10,101A - read color page state: BL = paging mode, BH = current page
+            let (sixteenPages,page)=pvga.getColorPageState();
+            pcpu.bx=((page as u16)<<8)|(if sixteenPages { 1 } else { 0 });
+        }
+        else if subFunction==0x1b
+        {
+            // INT 10,101B - CX DAC registers from BX to gray shades, weighting 30% red, 59% green, 11% blue
+            for idx in 0..pcpu.cx
+            {
+                let dacIdx=(pcpu.bx.wrapping_add(idx)&0xff) as u8;
+                let (red,green,blue)=pvga.getDacEntry(dacIdx);
+                let gray=((((red as u32)*30)+((green as u32)*59)+((blue as u32)*11)+50)/100) as u8;
+                pvga.setDacEntry(dacIdx,gray,gray,gray);
+            }
+        }
+    }
+
+    fn writeFontRomData(&mut self,pvga:&mut vga)
+    {
+        for idx in 0..(256*14)
+        {
+            let glyphLine=pvga.romFont8x14[idx];
+            self.writeMemory(0xc000,VIDEO_FONT8X14_OFFS+(idx as u16),glyphLine,pvga);
+        }
+        for idx in 0..(256*8)
+        {
+            let glyphLine=pvga.romFont8x8[idx];
+            self.writeMemory(0xc000,VIDEO_FONT8X8_OFFS+(idx as u16),glyphLine,pvga);
+        }
+        for idx in 0..(256*16)
+        {
+            let glyphLine=pvga.romFont8x16[idx];
+            self.writeMemory(0xc000,VIDEO_FONT8X16_OFFS+(idx as u16),glyphLine,pvga);
+        }
+
+        // 9 dots alternate chars list: char number followed by its glyph, 0 terminated. No chars in ours
+        self.writeMemory(0xc000,VIDEO_FONTALT_OFFS,0,pvga);
+    }
+
+    // INT 10,1130 - BH = font pointer wanted: 0 INT 1Fh, 1 INT 43h, 2 ROM 8x14, 3 ROM 8x8, 4 ROM 8x8 chars 80h-ffh, 
+    // 5 ROM 9x14 alternate, 6 ROM 8x16, 7 ROM 9x16 alternate
+    // returns ES:BP = font, CX = char height, DL = rows-1
+    fn getFontInformation(&mut self,pcpu:&mut x86cpu,pvga:&mut vga)
+    {
+        let bh=pcpu.bx>>8;
+        if (bh==0) || (bh==1)
+        {
+            let vecNum=if bh==0 { 0x1f } else { 0x43 };
+            pcpu.bp=self.readMemory16(0,vecNum*4,pvga);
+            pcpu.es=self.readMemory16(0,(vecNum*4)+2,pvga);
+        }
+        else
+        {
+            let fontOffs=[VIDEO_FONT8X14_OFFS,VIDEO_FONT8X8_OFFS,VIDEO_FONT8X8_OFFS+0x400,VIDEO_FONTALT_OFFS,VIDEO_FONT8X16_OFFS,VIDEO_FONTALT_OFFS];
+            pcpu.es=0xc000;
+            pcpu.bp=fontOffs[std::cmp::min((bh-2) as usize,5)];
+        }
+
+        pcpu.cx=pvga.getCharHeight();
+        pcpu.dx=(pcpu.dx&0xff00)|((pvga.getNumberOfRows()-1)&0xff);
+    }
+
+    // VBE 1.2 data lives in the (otherwise empty) video BIOS ROM area at c000:0000
+    fn writeVesaRomData(&mut self,pvga:&mut vga)
+    {
+        let oemString="dream86 VBE";
+        for (idx,ch) in oemString.bytes().enumerate()
+        {
+            self.writeMemory(0xc000,VESA_OEMSTRING_OFFS+(idx as u16),ch,pvga);
+        }
+        self.writeMemory(0xc000,VESA_OEMSTRING_OFFS+(oemString.len() as u16),0,pvga);
+
+        // video modes list, 0xffff terminated
+        self.writeMemory16(0xc000,VESA_MODELIST_OFFS,0x101,pvga);
+        self.writeMemory16(0xc000,VESA_MODELIST_OFFS+2,0x103,pvga);
+        self.writeMemory16(0xc000,VESA_MODELIST_OFFS+4,0xffff,pvga);
+
+        // window function, to be far called: mov ax,4f05h - int 10h - retf
+        let winFunc:[u8;6]=[0xb8,0x05,0x4f,0xcd,0x10,0xcb];
+        for idx in 0..winFunc.len()
+        {
+            self.writeMemory(0xc000,VESA_WINFUNC_OFFS+(idx as u16),winFunc[idx],pvga);
+        }
+    }
+
+    fn handleVesaINT(&mut self,pcpu:&mut x86cpu,pvga:&mut vga)
+    {
+        let subFunction=pcpu.ax&0xff;
+        let es=pcpu.es;
+        let di=pcpu.di;
+
+        if subFunction==0x00
+        {
+            // INT 10,4F00 - return VBE controller information at ES:DI
+            for idx in 0..256
+            {
+                self.writeMemory(es,di.wrapping_add(idx),0,pvga);
+            }
+
+            let signature="VESA";
+            for (idx,ch) in signature.bytes().enumerate()
+            {
+                self.writeMemory(es,di.wrapping_add(idx as u16),ch,pvga);
+            }
+            self.writeMemory16(es,di.wrapping_add(0x04),0x0102,pvga);
+            self.writeMemory16(es,di.wrapping_add(0x06),VESA_OEMSTRING_OFFS,pvga);
+            self.writeMemory16(es,di.wrapping_add(0x08),0xc000,pvga);
+            self.writeMemory16(es,di.wrapping_add(0x0e),VESA_MODELIST_OFFS,pvga);
+            self.writeMemory16(es,di.wrapping_add(0x10),0xc000,pvga);
+            self.writeMemory16(es,di.wrapping_add(0x12),16,pvga); // 16 blocks of 64k
+
+            pcpu.ax=0x004f;
+        }
+        else if subFunction==0x01
+        {
+            // INT 10,4F01 - return mode information at ES:DI for mode CX
+            let vesaMode=pcpu.cx&0x1ff;
+            if let Some((resx,resy))=vga::getVesaResolution(vesaMode)
+            {
+                for idx in 0..256
+                {
+                    self.writeMemory(es,di.wrapping_add(idx),0,pvga);
+                }
+
+                let numPages=(0x100000/(resx*resy)) as u8;
+                self.writeMemory16(es,di,0x001b,pvga); // supported, extended info, color, graphics
+                self.writeMemory(es,di.wrapping_add(0x02),0x07,pvga); // window A exists, readable, writable
+                self.writeMemory16(es,di.wrapping_add(0x04),64,pvga); // granularity in Kb
+                self.writeMemory16(es,di.wrapping_add(0x06),64,pvga); // window size in Kb
+                self.writeMemory16(es,di.wrapping_add(0x08),0xa000,pvga);
+                self.writeMemory16(es,di.wrapping_add(0x0c),VESA_WINFUNC_OFFS,pvga);
+                self.writeMemory16(es,di.wrapping_add(0x0e),0xc000,pvga);
+                self.writeMemory16(es,di.wrapping_add(0x10),resx as u16,pvga); // bytes per scanline
+                self.writeMemory16(es,di.wrapping_add(0x12),resx as u16,pvga);
+                self.writeMemory16(es,di.wrapping_add(0x14),resy as u16,pvga);
+                self.writeMemory(es,di.wrapping_add(0x16),8,pvga); // char cell
+                self.writeMemory(es,di.wrapping_add(0x17),16,pvga);
+                self.writeMemory(es,di.wrapping_add(0x18),1,pvga); // planes
+                self.writeMemory(es,di.wrapping_add(0x19),8,pvga); // bits per pixel
+                self.writeMemory(es,di.wrapping_add(0x1a),1,pvga); // banks
+                self.writeMemory(es,di.wrapping_add(0x1b),4,pvga); // packed pixel
+                self.writeMemory(es,di.wrapping_add(0x1d),numPages-1,pvga); // image pages
+                self.writeMemory(es,di.wrapping_add(0x1e),1,pvga);
+
+                pcpu.ax=0x004f;
+            }
+            else
+            {
+                pcpu.ax=0x014f;
+            }
+        }
+        else if subFunction==0x02
+        {
+            // INT 10,4F02 - set mode BX, bit 15 set = don't clear video memory
+            let vesaMode=pcpu.bx&0x1ff;
+            if vesaMode<0x100
+            {
+                pvga.setVideomode(vesaMode&0x7f);
+                pcpu.ax=0x004f;
+            }
+            else if pvga.setVesaMode(vesaMode,(pcpu.bx&0x8000)==0)
+            {
+                pcpu.ax=0x004f;
+            }
+            else
+            {
+                pcpu.ax=0x014f;
+            }
+        }
+        else if subFunction==0x03
+        {
+            // INT 10,4F03 - return current mode in BX
+            pcpu.bx=pvga.mode;
+            pcpu.ax=0x004f;
+        }
+        else if subFunction==0x05
+        {
+            // INT 10,4F05 - BH=0 set window BL to DX, BH=1 get window BL in DX
+            // only window A is there
+            if (pcpu.bx&0xff)!=0
+            {
+                pcpu.ax=0x014f;
+            }
+            else if (pcpu.bx>>8)==0x01
+            {
+                pcpu.dx=pvga.vesaWindowPos as u16;
+                pcpu.ax=0x004f;
+            }
+            else if pvga.isVesaMode() && pvga.setVesaWindow(pcpu.dx as usize)
+            {
+                pcpu.ax=0x004f;
+            }
+            else
+            {
+                pcpu.ax=0x014f;
+            }
+        }
+        else
+        {
+            // not supported
+            pcpu.ax=0x014f;
+        }
+    }
+
+    // word at 40:ofs in the BIOS data area
+    fn getBDAWord(&self,ofs:usize) -> u16
+    {
+        return (self.ram[0x400+ofs] as u16)|((self.ram[0x401+ofs] as u16)<<8);
+    }
+
+    fn setBDAWord(&mut self,ofs:usize,val:u16)
+    {
+        self.ram[0x400+ofs]=(val&0xff) as u8;
+        self.ram[0x401+ofs]=(val>>8) as u8;
+    }
+
+    // the bits are described at INT 11
+    fn getEquipmentWord(&self,pdisk:&fddController) -> u16
+    {
+        // 1 printer, game adapter, 80x25 color; no math coprocessor, the FPU opcodes are just stubs
+        let mut equipment:u16=0x5024;
+
+        // no UART is emulated, there are no COM ports to report
+        let numSerialPorts:u16=0;
+        equipment|=numSerialPorts<<9;
+        if (self.machType==machineType::machineMDA) || (self.machType==machineType::machineHercules)
+        {
+            equipment|=0x30; // 80x25 monochrome
+        }
+
+        let numFloppies=pdisk.getNumberOfDrives(false) as u16;
+        if numFloppies>0
+        {
+            equipment|=0x01|((numFloppies-1)<<6);
+        }
+
+        return equipment;
+    }
+
+    // the video BIOS ROM contents at c000:0000, written once as nothing changes them afterwards
+    pub fn initVideoRom(&mut self,pvga:&mut vga)
+    {
+        self.writeFontRomData(pvga);
+        if self.machType==machineType::machineEGA
+        {
+            self.writeVesaRomData(pvga);
+        }
+    }
+
+    // BIOS data area fields that come from the machine configuration rather than from the BIOS
+    pub fn initBDA(&mut self,pdisk:&fddController,pvga:&vga)
+    {
+        // 40:08 LPT1 base port
+        self.setBDAWord(0x08,0x378);
+        // 40:10 equipment word
+        let equipment=self.getEquipmentWord(pdisk);
+        self.setBDAWord(0x10,equipment);
+        // 40:13 conventional memory in KB
+        self.setBDAWord(0x13,self.conventionalKb);
+        // 40:17 keyboard flags, no shift key down and no lock active
+        self.ram[0x417]=0;
+        self.ram[0x418]=0;
+
+        self.updateVideoBDA(pvga);
+    }
+
+    // the BIOS POST ends giving control to the boot sector at 0000:7c00, its data area is complete then
+    pub fn bootSectorStarted(&mut self,pdisk:&fddController,pvga:&vga)
+    {
+        if (!self.biosLoaded) || self.bdaInitDone { return; }
+
+        self.bdaInitDone=true;
+        self.initBDA(pdisk,pvga);
+    }
+
+    /*
+        the shift keys the frontend sees down, the lock bits of 40:17 are left alone
+        bit 0 right shift, bit 1 left shift, bit 2 ctrl, bit 3 alt
+    */
+    pub fn setShiftKeys(&mut self,keys:u8)
+    {
+        self.ram[0x417]=(self.ram[0x417]&0xf0)|(keys&0x0f);
+    }
+
+    // one timer tick in 40:6C, at 24 hours (1800B0h ticks) it wraps and 40:70 says midnight passed
+    fn incTickCount(&mut self)
+    {
+        let mut ticks:u32=(self.getBDAWord(0x6c) as u32)|((self.getBDAWord(0x6e) as u32)<<16);
+        ticks+=1;
+        if ticks>=0x1800b0
+        {
+            ticks=0;
+            self.ram[0x470]=1;
+        }
+        self.setBDAWord(0x6c,(ticks&0xffff) as u16);
+        self.setBDAWord(0x6e,(ticks>>16) as u16);
+    }
+
+    /*
+        programs may move a cursor writing 40:50 directly, the BIOS must start from there.
+        40:50 has room for 8 pages: pages 8-15 of the 40 columns modes (32k of EGA/VGA text memory)
+        keep their cursor only in the card, INT 10h AH=02/03 still set and get it
+    */
+    fn loadCursorsFromBDA(&mut self,pvga:&mut vga)
+    {
+        for page in 0..8
+        {
+            pvga.pageCursors[page]=(self.ram[0x450+(page*2)] as usize,self.ram[0x451+(page*2)] as usize);
+        }
+    }
+
+    // keeps the video fields of the BIOS data area in line with the emulated card
+    fn updateVideoBDA(&mut self,pvga:&vga)
+    {
+        // 40:49 video mode (the VESA ones don't fit), 40:4A columns
+        if pvga.mode<=0xff
+        {
+            self.ram[0x449]=pvga.mode as u8;
+        }
+        self.setBDAWord(0x4a,pvga.getNumberOfColumns());
+
+        // 40:4C page size, 40:4E current page offset
+        let pageSize=pvga.getPageSize() as u16;
+        let pageOffset=pvga.getPageOffset(pvga.activePage) as u16;
+        self.ram[0x44c]=(pageSize&0xff) as u8;
+        self.ram[0x44d]=(pageSize>>8) as u8;
+        self.ram[0x44e]=(pageOffset&0xff) as u8;
+        self.ram[0x44f]=(pageOffset>>8) as u8;
+
+        // 40:50 cursor position (column,row) of the first 8 pages, see loadCursorsFromBDA
+        for page in 0..8
+        {
+            let (cx,cy)=pvga.getCursorPosition(page);
+            self.ram[0x450+(page*2)]=cx as u8;
+            self.ram[0x451+(page*2)]=cy as u8;
+        }
+
+        // 40:60 cursor ending and starting scanline
+        let cursorShape=pvga.getCursorShape();
+        self.ram[0x460]=(cursorShape&0xff) as u8;
+        self.ram[0x461]=(cursorShape>>8) as u8;
+
+        // 40:62 active page
+        self.ram[0x462]=pvga.activePage as u8;
+
+        // 40:63 CRTC index port, 40:65 mode control register, 40:66 CGA color select
+        if pvga.isMonoAdapter()
+        {
+            self.setBDAWord(0x63,0x3b4);
+            self.ram[0x465]=pvga.herculesModeReg;
+        }
+        else
+        {
+            self.setBDAWord(0x63,0x3d4);
+            self.ram[0x465]=pvga.cgaModeReg;
+        }
+        self.ram[0x466]=pvga.cgaColorReg;
+
+        // 40:84 rows on screen minus one, 40:85 char height
+        let charHeight=pvga.getCharHeight();
+        self.ram[0x484]=(pvga.getNumberOfRows()-1) as u8;
+        self.ram[0x485]=(charHeight&0xff) as u8;
+        self.ram[0x486]=(charHeight>>8) as u8;
+    }
+
     // returns true if we should go on with the code
     pub fn handleINT(&mut self,intNum:u8,pcpu:&mut x86cpu,pvga:&mut vga,pdisk:&fddController) -> bool
+    {
+        // INT 10 and INT 29 move the cursors, 40:50 is where they are for the programs too
+        let usesCursors=(intNum==0x10) || (intNum==0x29);
+        if usesCursors
+        {
+            self.loadCursorsFromBDA(pvga);
+        }
+        let goOn=self.handleBiosINT(intNum,pcpu,pvga,pdisk);
+        if usesCursors
+        {
+            self.updateVideoBDA(pvga);
+        }
+        return goOn;
+    }
+
+    // the BIOS services emulated here, the video ones change the data area
+    fn handleBiosINT(&mut self,intNum:u8,pcpu:&mut x86cpu,pvga:&mut vga,pdisk:&fddController) -> bool
     {
         if intNum==0x10
         {
@@ -280,26 +883,22 @@ impl machine
             {
                 // set videomode
                 // = 8x  EGA, MCGA or VGA ignore bit 7
-                println!("vga::setting videomode {:02x} at {:04x}:{:04x}",pcpu.ax&0xff,pcpu.cs,pcpu.ip);
+                emuLog::trace(&format!("vga::setting videomode {:02x} at {:04x}:{:04x}",pcpu.ax&0xff,pcpu.cs,pcpu.ip));
                 pvga.setVideomode(pcpu.ax&0x7f);
                 return true;
             }
             else if (pcpu.ax&0xff00)==0x1000
             {
                 // INT 10,10 - Set/Get Palette Registers (EGA/VGA)
-                // TODO
-                if (pcpu.ax&0xff)==0
-                {
-                    //println!("Setting DAC reg BH {:02x} BL {:02x}",pcpu.bx>>8,pcpu.bx&0xff);
-                    //process::exit(0x0100);
-                }
-
+                self.handlePaletteINT(pcpu,pvga);
                 return true;
             }
             else if (pcpu.ax&0xff00)==0x0100
             {
-                // set cursor type
-                // TODO
+                // INT 10,1 - Set Cursor Type
+                // CH = cursor starting scan line (bits 0-4), bits 5-6 blink/hide (2000h hides the cursor)
+                // CL = cursor ending scan line
+                pvga.setCursorShape((pcpu.cx>>8) as u8,(pcpu.cx&0xff) as u8);
                 return true;
             }
             else if (pcpu.ax&0xff00)==0x0900
@@ -323,15 +922,50 @@ impl machine
             else if (pcpu.ax&0xff00)==0x0800
             {
                 // INT 10,8 - Read Character and Attribute at Cursor Position
-                pcpu.ax=pvga.readCharAttributeAtCursorPos();
+                // BH = display page
+                pcpu.ax=pvga.readCharAttributeAtCursorPos((pcpu.bx>>8) as usize);
                 return true;
             }
             else if (pcpu.ax&0xff00)==0x1100
             {
                 // INT 10,11 - Character Generator Routine (EGA/VGA)
-                // TODO
-                //println!("warning: int10h is trying to remap character set {:04x}",pcpu.ax);
-                //process::exit(0x0100);
+                // AL = 00h/10h load user font: ES:BP = table, CX = number of chars, DX = first char, BL = block, BH = bytes per char
+                // AL = 01h/11h load ROM 8x14 font, 02h/12h 8x8, 04h/14h 8x16 in block BL
+                // AL = 1xh also sets the char height and so the number of rows
+                // AL = 03h set block specifier BL
+                // AL = 22h/23h/24h ROM 8x14/8x8/8x16 graphics font, BL = rows code, DL = rows if BL=0
+                // AL = 30h font information
+                let al=pcpu.ax&0xff;
+                let bl=(pcpu.bx&0xff) as u8;
+                if (al==0x00) || (al==0x10)
+                {
+                    let charHeight=(pcpu.bx>>8) as usize;
+                    let mut glyphs:Vec<u8>=Vec::new();
+                    for idx in 0..(pcpu.cx as usize)*charHeight
+                    {
+                        glyphs.push(self.readMemory(pcpu.es,pcpu.bp.wrapping_add(idx as u16),pvga));
+                    }
+                    pvga.loadFontGlyphs(&glyphs,charHeight,pcpu.dx as usize,pcpu.cx as usize,bl);
+                    if al==0x10 { pvga.setTextCharHeight(charHeight); }
+                }
+                else if (al&0xef)==0x01 { pvga.loadRomTextFont(14,bl,al==0x11); }
+                else if (al&0xef)==0x02 { pvga.loadRomTextFont(8,bl,al==0x12); }
+                else if (al&0xef)==0x04 { pvga.loadRomTextFont(16,bl,al==0x14); }
+                else if al==0x03 { pvga.setFontBlockSpecifier(bl); }
+                else if (al==0x22) || (al==0x23) || (al==0x24)
+                {
+                    // INT 43h points to the graphics font
+                    let (charHeight,fontOffs)=if al==0x22 { (14,VIDEO_FONT8X14_OFFS) } 
+                                              else if al==0x23 { (8,VIDEO_FONT8X8_OFFS) } 
+                                              else { (16,VIDEO_FONT8X16_OFFS) };
+                    pvga.setGraphicsFont(charHeight,bl,(pcpu.dx&0xff) as u8);
+                    self.writeMemory16(0,0x43*4,fontOffs,pvga);
+                    self.writeMemory16(0,(0x43*4)+2,0xc000,pvga);
+                }
+                else if al==0x30
+                {
+                    self.getFontInformation(pcpu,pvga);
+                }
                 return true;
             }
             else if (pcpu.ax&0xff00)==0x0e00
@@ -359,61 +993,82 @@ impl machine
 
                 pvga.putpixel(color,column,row); 
             }
-            else if (pcpu.ax&0xff00)==0x0600
+            else if ((pcpu.ax&0xff00)==0x0600) || ((pcpu.ax&0xff00)==0x0700)
             {
                 // INT 10,6 - Scroll Window Up
-                // TODO - for now, clears the screen
-                pvga.clrScreenMode2();
+                // INT 10,7 - Scroll Window Down
+                // AL = number of lines to scroll (0 = blank window)
+                // BH = attribute used to write blank lines
+                // CH,CL = row,column of upper left corner
+                // DH,DL = row,column of lower right corner
+                pvga.scrollWindow((pcpu.ax&0xff00)==0x0600,pcpu.ax&0xff,pcpu.cx>>8,pcpu.cx&0xff,pcpu.dx>>8,pcpu.dx&0xff,(pcpu.bx>>8) as u8);
                 return true;
             }
             else if (pcpu.ax&0xff00)==0x0f00
             {
                 // INT 10,F - Get Video State
                 let numc=pvga.getNumberOfColumns();
-                let videomode=pvga.mode;
+                let videomode=pvga.mode&0xff;
                 pcpu.ax=videomode|(numc<<8);
-                pcpu.bx=pcpu.bx&0xff;
+                pcpu.bx=(pcpu.bx&0xff)|((pvga.activePage as u16)<<8);
                 return true;
             }
             else if (pcpu.ax&0xff00)==0x0b00
             {
                 // INT 10,B - Set Color Palette
-                // TODO
+                // BH = 0: BL = background/border color (bit 4 = intensity)
+                // BH = 1: BL = palette (0 or 1)
+                let bl=(pcpu.bx&0xff) as u8;
+                if (pcpu.bx>>8)==0
+                {
+                    pvga.write0x3d9((pvga.cgaColorReg&0x20)|(bl&0x1f));
+                }
+                else
+                {
+                    pvga.write0x3d9((pvga.cgaColorReg&0x1f)|((bl&0x01)<<5));
+                }
                 return true;
             }
             else if (pcpu.ax&0xff00)==0x0200
             {
                 // INT 10,2 - Set Cursor Position
+                // BH = page number
                 // DH = row
                 // DL = column
-                pvga.setCursorPosition(pcpu.dx&0xff,pcpu.dx>>8);
+                pvga.setCursorPosition(pcpu.dx&0xff,pcpu.dx>>8,(pcpu.bx>>8) as usize);
                 return true;
             }
             else if (pcpu.ax&0xff00)==0x0300
             {
                 // INT 10,3 - Read Cursor Position and Size
                 // DH = row
-	            // DL = column
-                let cp=pvga.getCursorPosition();
+    	            // DL = column
+                let cp=pvga.getCursorPosition((pcpu.bx>>8) as usize);
                 pcpu.dx=(cp.0 as u16)|((cp.1 as u16)<<8);
-                pcpu.cx=0x0607; // TODO, I don't think it's the same for all videomodes
+                pcpu.cx=pvga.getCursorShape();
                 return true;
             }
             else if (pcpu.ax&0xff00)==0x0500
             {
                 // INT 10,5 - Select Active Display Page
-                // TODO
-                return true;
-            }
-            else if (pcpu.ax&0xff00)==0x0700
-            {
-                // INT 10,7 - Scroll Window Down
-                // TODO
+                // AL = new page number
+                pvga.selectActivePage((pcpu.ax&0xff) as usize);
                 return true;
             }
             else if (pcpu.ax&0xff00)==0x1200
             {
                 // INT 10,12 - Video Subsystem Configuration (EGA/VGA only)
+                if (pcpu.bx&0xff)==0x30
+                {
+                    // BL=30h - select vertical resolution for text modes, AL = 0 200, 1 350, 2 400 scanlines
+                    // AL=12h if the function is supported
+                    if pvga.selectTextScanlines((pcpu.ax&0xff) as u8)
+                    {
+                        pcpu.ax=(pcpu.ax&0xff00)|0x12;
+                    }
+                    return true;
+                }
+
                 if self.machType==machineType::machineEGA
                 {
                     pcpu.bx=0x0003;
@@ -423,7 +1078,7 @@ impl machine
                 {
                     pcpu.cx=0x0005; // CGA
                 }
-                
+            
                 return true;
             }
             else if (pcpu.ax&0xff00)==0x1a00
@@ -477,12 +1132,20 @@ impl machine
                 // TODO
                 return true;
             }
+            else if (pcpu.ax&0xff00)==0x4f00
+            {
+                // INT 10,4F - VESA BIOS extensions
+                if self.machType==machineType::machineEGA
+                {
+                    self.handleVesaINT(pcpu,pvga);
+                }
+                return true;
+            }
             else
             {
-                println!("Unknown interrupt");
-                println!("{:02x},{:02x} AL={:02x}",intNum,pcpu.ax>>8,pcpu.ax&0xff);
-                process::exit(0x0100);
+                emuLog::fatalError(&format!("Unknown interrupt\n{:02x},{:02x} AL={:02x}",intNum,pcpu.ax>>8,pcpu.ax&0xff),emuLog::EXIT_ABORTED);
             }
+
         }
         else if intNum==0x13
         {
@@ -508,22 +1171,18 @@ impl machine
                 let loAddr=pcpu.bx;
                 let hiAddr=pcpu.es;
 
-                if driveNumber!=0
+                if numOfSectorsToRead==0
                 {
-                    //println!("INT 13,2: Trying to read sectors from a drive that is not A:");
-                    //process::exit(0x0100);
-                    //pcpu.ax=0x0700;
-                    //pcpu.setCflag(true);
-                    //return true;
+                    emuLog::fatalError("Trying to read 0 sectors",emuLog::EXIT_ABORTED);
                 }
 
-                if numOfSectorsToRead==0
+                if !pdisk.readDiskSectors(self,pvga,driveNumber as u8,numOfSectorsToRead,sectorNumber,cylinderNumber,headNumber,loAddr,hiAddr)
                 {
-                    println!("Trying to read 0 sectors");
-                    process::exit(0x0100);
+                    // no image for the drive: timeout, nothing read
+                    pcpu.ax=0x8000;
+                    pcpu.setCflag(true);
+                    return true;
                 }
-
-                pdisk.readDiskSectors(self,pvga,numOfSectorsToRead,sectorNumber,cylinderNumber,headNumber,loAddr,hiAddr);
         
                 pcpu.ax=numOfSectorsToRead as u16;
                 pcpu.setCflag(false); // CF = 0 if successful
@@ -534,7 +1193,7 @@ impl machine
             {
                 // INT 13,8 - Get Current Drive Parameters (XT & newer)   
                 // DL = drive number (0=A:, 1=2nd floppy, 80h=drive 0, 81h=drive 1)
-                if (pcpu.dx&0xff)==0x80
+                if ((pcpu.dx&0xff)>=0x80) && pdisk.isDrivePresent((pcpu.dx&0xff) as u8)
                 {
                     // hard drive
 /*                    
@@ -550,7 +1209,7 @@ impl machine
 */                       
                     pcpu.bx=(pcpu.bx&0xff00)|0x80;
                     pcpu.cx=0xe87f;
-                    pcpu.dx=0x0f01;
+                    pcpu.dx=0x0f00|(pdisk.getNumberOfDrives(true) as u16);
                     pcpu.ax=0;//pcpu.ax&0xff;
                     pcpu.setCflag(false);
                     //pcpu.ax=(pcpu.ax&0xff)|(0x07<<8);
@@ -629,9 +1288,7 @@ impl machine
             }
             else
             {
-                println!("Unknown interrupt");
-                println!("{:02x},{:02x}",intNum,pcpu.ax>>8);
-                process::exit(0x0100);
+                emuLog::fatalError(&format!("Unknown interrupt\n{:02x},{:02x}",intNum,pcpu.ax>>8),emuLog::EXIT_ABORTED);
             }
         }
         else if intNum==0x11
@@ -668,20 +1325,45 @@ impl machine
                         10 - 3 drive	     11 - 4 drives                    
             */            
 
-            //pcpu.ax=0x5115; // 101 0100 0100 0101
-            pcpu.ax=0x5426;
+            // from 40:10, set up at boot by initBDA
+            pcpu.ax=self.getBDAWord(0x10);
             return true;
         }
         else if intNum==0x5
         {
             // INT 5 printscreen
-            // TODO
+            // status byte at 50:00 - 0 ok, 1 in progress, ff error
+            if !self.lpt1.isAttached()
+            {
+                self.ram[0x500]=0xff;
+                return true;
+            }
+
+            self.ram[0x500]=1;
+            if pvga.isTextMode()
+            {
+                // the screen as displayed: page, start address and rows from the CRTC
+                let (numColumns,numRows,_charHeight)=pvga.getTextGeometry();
+                for row in 0..numRows
+                {
+                    for col in 0..numColumns
+                    {
+                        let mut ch=pvga.getScreenCell(col,row).0;
+                        if ch==0 { ch=0x20; }
+                        self.lpt1.printByte(ch);
+                    }
+                    self.lpt1.printByte(0x0d);
+                    self.lpt1.printByte(0x0a);
+                }
+            }
+            self.ram[0x500]=0;
             return true;            
         }
         else if intNum==0x12
         {
             // INT 12 - Memory Size Determination
-            pcpu.ax=0x280; // TODO configurable size
+            // from 40:13, like a real BIOS, so programs reserving memory there are seen
+            pcpu.ax=self.getBDAWord(0x13);
             return true;            
         }
         else if intNum==0x14
@@ -734,6 +1416,38 @@ impl machine
                 pcpu.setCflag(false);
                 return true;
             }
+            else if (pcpu.ax&0xff00)==0x8400
+            {
+                // INT 15,84 - Joystick Support (XT after 11/8/82, AT, XT286, PS/2)
+                // DX = 0 to read the current switch settings in AL bits 4-7
+                //    = 1 to read the resistive inputs in AX, BX, CX, DX
+                if !self.gameport.isAnyConnected()
+                {
+                    pcpu.setCflag(true);
+                    return true;
+                }
+
+                if pcpu.dx==0
+                {
+                    pcpu.ax=(pcpu.ax&0xff00)|(self.gameport.getButtonBits() as u16);
+                }
+                else if pcpu.dx==1
+                {
+                    let positions=self.gameport.getBiosPositions();
+                    pcpu.ax=positions[0];
+                    pcpu.bx=positions[1];
+                    pcpu.cx=positions[2];
+                    pcpu.dx=positions[3];
+                }
+                else
+                {
+                    pcpu.setCflag(true);
+                    return true;
+                }
+
+                pcpu.setCflag(false);
+                return true;
+            }
             else if (pcpu.ax&0xff00)==0x8700
             {
                 // INT 15,87 - Move Block to/from Extended Memory            
@@ -764,15 +1478,39 @@ impl machine
             }
             else
             {
-                println!("Unknown interrupt 0x15");
-                println!("{:02x},{:02x}",intNum,pcpu.ax>>8);
-                process::exit(0x0100);
+                emuLog::fatalError(&format!("Unknown interrupt 0x15\n{:02x},{:02x}",intNum,pcpu.ax>>8),emuLog::EXIT_ABORTED);
             }
         }
         else if intNum==0x17
         {
-            // INT 17,1 - Initialize Printer Port
-            // TODO
+            // printer services, DX = printer number (only LPT1 is there)
+            // AH on return = printer status
+            if pcpu.dx!=0
+            {
+                pcpu.ax=(pcpu.ax&0xff)|(0x01<<8); // time out
+                return true;
+            }
+
+            if (pcpu.ax&0xff00)==0
+            {
+                // INT 17,0 - Print Character
+                self.lpt1.printByte((pcpu.ax&0xff) as u8);
+                let mut status=self.lpt1.getBiosStatus();
+                if !self.lpt1.isAttached() { status|=0x01; }
+                pcpu.ax=(pcpu.ax&0xff)|((status as u16)<<8);
+            }
+            else if (pcpu.ax&0xff00)==0x0100
+            {
+                // INT 17,1 - Initialize Printer Port
+                self.lpt1.resetPrinter();
+                pcpu.ax=(pcpu.ax&0xff)|((self.lpt1.getBiosStatus() as u16)<<8);
+            }
+            else if (pcpu.ax&0xff00)==0x0200
+            {
+                // INT 17,2 - Read Printer Port Status
+                pcpu.ax=(pcpu.ax&0xff)|((self.lpt1.getBiosStatus() as u16)<<8);
+            }
+
             return true;
         }
         else if intNum==0x1a
@@ -780,15 +1518,21 @@ impl machine
             // read system clock counter, AH=0
             if (pcpu.ax&0xff00)==0
             {
-                pcpu.ax&=0xff00; // midnight flag 0 in AL
-                pcpu.cx=((self.clockTicker&0xffff0000)>>16) as u16;
-                pcpu.dx=(self.clockTicker&0xffff) as u16;
+                // ticks from 40:6C, the midnight flag at 40:70 goes to AL and is cleared
+                pcpu.ax=(pcpu.ax&0xff00)|(self.ram[0x470] as u16);
+                self.ram[0x470]=0;
+                pcpu.cx=self.getBDAWord(0x6e);
+                pcpu.dx=self.getBDAWord(0x6c);
                 return true;
             }
             else if (pcpu.ax&0xff00)==0x0100
             {
                 // INT 1A,1 - Set System Clock Counter
-                // TODO
+                // CX = high word, DX = low word of the count
+                self.setBDAWord(0x6e,pcpu.cx);
+                self.setBDAWord(0x6c,pcpu.dx);
+                self.ram[0x470]=0;
+                self.clockTicker=((pcpu.cx as u64)<<16)|(pcpu.dx as u64);
                 return true;
             }
             else if (pcpu.ax&0xff00)==0x0200
@@ -836,9 +1580,7 @@ impl machine
             }
             else
             {
-                println!("Unknown interrupt");
-                println!("{:02x},{:02x}",intNum,pcpu.ax>>8);
-                process::exit(0x0100);
+                emuLog::fatalError(&format!("Unknown interrupt\n{:02x},{:02x}",intNum,pcpu.ax>>8),emuLog::EXIT_ABORTED);
             }
         }
         else if intNum==0x29
@@ -896,21 +1638,7 @@ impl machine
                 `----------- insert is active                
                 */
 
-                let mut al=0;
-                if self.keyboardQueue.len()==0
-                {
-                    pcpu.ax=0xff00;   
-                }
-                else
-                {
-                    let scanCode:u16=self.keyboardQueue[self.keyboardQueue.len()-1];
-                    if scanCode==0xff
-                    {
-                        al|=2;
-                        self.keyboardQueue.pop();
-                    }
-                    pcpu.ax=0xff00|al;
-                }
+                pcpu.ax=0xff00|(self.ram[0x417] as u16);
 
                 return true;
             }
@@ -932,16 +1660,12 @@ impl machine
             }
             else
             {
-                println!("Unknown interrupt");
-                println!("{:02x},{:02x}",intNum,pcpu.ax>>8);
-                process::exit(0x0100);
+                emuLog::fatalError(&format!("Unknown interrupt\n{:02x},{:02x}",intNum,pcpu.ax>>8),emuLog::EXIT_ABORTED);
             }
         }
         else
         {
-            println!("Unknown interrupt");
-            println!("{:02x},{:02x} at {:04x}:{:04x}",intNum,pcpu.ax>>8,pcpu.cs,pcpu.ip);
-            process::exit(0x0100);
+            emuLog::fatalError(&format!("Unknown interrupt\n{:02x},{:02x} at {:04x}:{:04x}",intNum,pcpu.ax>>8,pcpu.cs,pcpu.ip),emuLog::EXIT_ABORTED);
         }
 
         return true;
@@ -955,8 +1679,8 @@ impl machine
         ofs=ofs.wrapping_sub(1);
         let flatAddrm2:i64=(ofs as i64)+((seg as i64)*16);
 
-        self.ram[(flatAddrm2) as usize]=(val&0xff) as u8;
-        self.ram[(flatAddrm1) as usize]=((val>>8)&0xff) as u8;
+        self.writeRam(flatAddrm2,(val&0xff) as u8);
+        self.writeRam(flatAddrm1,((val>>8)&0xff) as u8);
 
         self.stackey.push((val&0xff) as u8);
         self.stackey.push(((val>>8)&0xff) as u8);
@@ -971,8 +1695,8 @@ impl machine
 
         let mut retval:u16=0;
 
-        retval|=self.ram[(flatAddr) as usize] as u16;
-        let mut upperPart:u16=self.ram[flatAddrp1 as usize].into();
+        retval|=self.readRam(flatAddr) as u16;
+        let mut upperPart:u16=self.readRam(flatAddrp1).into();
         upperPart<<=8;
         retval|=upperPart;
 
@@ -982,19 +1706,38 @@ impl machine
         return retval;
     }
 
+    // between the installed RAM and the video memory there's nothing, the bus reads ff
+    fn isOpenBus(&self,flatAddr:i64) -> bool
+    {
+        let addr=flatAddr&0xfffff;
+        return (addr>=((self.conventionalKb as i64)*1024)) && (addr<0xa0000);
+    }
+
+    fn readRam(&self,flatAddr:i64) -> u8
+    {
+        if self.isOpenBus(flatAddr) { return 0xff; }
+        return self.ram[(flatAddr&0xfffff) as usize];
+    }
+
+    fn writeRam(&mut self,flatAddr:i64,val:u8)
+    {
+        if self.isOpenBus(flatAddr) { return; }
+        self.ram[(flatAddr&0xfffff) as usize]=val;
+    }
+
     pub fn readMemory(&self,segment:u16,address:u16,pvga:&mut vga) -> u8
     {
         let i64seg:i64=segment.into();
         let i64addr:i64=address.into();
         let flatAddr:i64=i64addr+(i64seg*16);
 
-        if ((flatAddr>=0xa0000) && (flatAddr<=0xaffff)) || ((flatAddr>=0xb8000) && (flatAddr<=0xbffff))
+        if ((flatAddr>=0xa0000) && (flatAddr<=0xaffff)) || ((flatAddr>=0xb0000) && (flatAddr<=0xbffff))
         {
             // VGA framebuffer
             return pvga.readMemory(flatAddr);
         }
 
-        return self.ram[(flatAddr&0xfffff) as usize];
+        return self.readRam(flatAddr);
     }
 
     pub fn readMemory16(&self,segment:u16,address:u16,pvga:&mut vga) -> u16
@@ -1003,13 +1746,13 @@ impl machine
         let i64addr:i64=address.into();
         let flatAddr:i64=i64addr+(i64seg*16);
 
-        if ((flatAddr>=0xa0000) && (flatAddr<=0xaffff)) || ((flatAddr>=0xb8000) && (flatAddr<=0xbffff))
+        if ((flatAddr>=0xa0000) && (flatAddr<=0xaffff)) || ((flatAddr>=0xb0000) && (flatAddr<=0xbffff))
         {
             return pvga.readMemory16(flatAddr);
         }
 
-        let lobyte:u16=self.ram[(flatAddr&0xfffff) as usize] as u16;
-        let hibyte:u16=self.ram[((flatAddr+1)&0xfffff) as usize] as u16;
+        let lobyte:u16=self.readRam(flatAddr) as u16;
+        let hibyte:u16=self.readRam(flatAddr+1) as u16;
 
         return lobyte|(hibyte<<8);
     }
@@ -1020,14 +1763,14 @@ impl machine
         let i64addr:i64=address.into();
         let flatAddr:i64=i64addr+(i64seg*16);
 
-        if ((flatAddr>=0xa0000) && (flatAddr<=0xaffff)) || ((flatAddr>=0xb8000) && (flatAddr<=0xbffff))
+        if ((flatAddr>=0xa0000) && (flatAddr<=0xaffff)) || ((flatAddr>=0xb0000) && (flatAddr<=0xbffff))
         {
             // VGA framebuffer
             pvga.writeMemory(flatAddr,val);
         }
         else
         {
-            self.ram[(flatAddr&0xfffff) as usize]=val;
+            self.writeRam(flatAddr,val);
         }
     }
 
@@ -1038,14 +1781,14 @@ impl machine
         let flatAddr:i64=i64addr+(i64seg*16);
 
         if ((flatAddr>=0xa0000) && (flatAddr<=0xaffff)) ||
-           ((flatAddr>=0xb8000) && (flatAddr<=0xbffff))
+           ((flatAddr>=0xb0000) && (flatAddr<=0xbffff))
         {
             pvga.writeMemory16(flatAddr,val);
         }
         else
         {
-            self.ram[(flatAddr&0xfffff) as usize]=(val&0xff) as u8;
-            self.ram[((flatAddr+1)&0xfffff) as usize]=(val>>8) as u8;
+            self.writeRam(flatAddr,(val&0xff) as u8);
+            self.writeRam(flatAddr+1,(val>>8) as u8);
         }
     }
 
@@ -1053,16 +1796,23 @@ impl machine
     {
         // todo: update 18.206 times per second
         // assume 200.000 instructions per seconds
+        self.gameport.update();
+
         self.internalClockTicker+=1;
         if self.internalClockTicker>=10000
         {
             self.internalClockTicker=0;
             self.clockTicker+=1;
+            if !self.biosLoaded
+            {
+                // the BIOS INT 8 handler counts the ticks in the data area, without it we do
+                self.incTickCount();
+            }
             pcpu.triggerHwIrq(8);
         }
     }
 
-    pub fn new(_comFullPath:&str,ramSize:usize,mode:u8,machType:machineType) -> Self 
+    pub fn new(_comFullPath:&str,biosFile:&str,ramSize:usize,conventionalKb:u16,mode:u8,machType:machineType) -> Self 
     {
         let mut machineRAM:Vec<u8>=Vec::with_capacity(ramSize);
         for _i in 0..ramSize
@@ -1071,7 +1821,7 @@ impl machine
             machineRAM.push(num as u8);
         }
 
-        if mode==0 { Self::loadBIOS(&mut machineRAM,"./bios/bios_cga"); }
+        if mode==0 { Self::loadBIOS(&mut machineRAM,biosFile); }
         else if mode==2 { Self::loadBinFile(&mut machineRAM,_comFullPath,0xf0000); }
         else { Self::loadCOMFile(&mut machineRAM,_comFullPath); }
 
@@ -1084,7 +1834,7 @@ impl machine
         let thestack:Vec<u8>=Vec::new();
         let kq:Vec<u16>=Vec::new();
 
-        machine 
+        machine
         {
             ram: machineRAM,
             stackey: thestack,
@@ -1093,7 +1843,12 @@ impl machine
             keyboardQueue: kq,
             lastScancode: 0,
             ppi_a: 0,
-            machType: machType
+            machType: machType,
+            conventionalKb: conventionalKb,
+            biosLoaded: mode==0,
+            bdaInitDone: false,
+            lpt1: parallelPort::new(),
+            gameport: gamePort::new()
         }
     }
 }
@@ -20,6 +20,17 @@ mod machine;
 mod x86cpu;
 mod fddController;
 mod guiif;
+mod cp437;
+mod parallelPort;
+mod gamePort;
+mod videoFrame;
+mod videoRecorder;
+mod videoScaler;
+mod headless;
+mod termif;
+mod config;
+mod speedLimiter;
+mod emuLog;
 
 //
 //
@@ -30,25 +41,93 @@ fn main()
     let mut _breakIt=false;
 
     let args: Vec<String> = env::args().collect();
-    if args.len()!=4
+    if args.len()<2
     {
-        println!("syntax: dream86.exe <disk image full path> <com name> <runmode>");        
-        process::exit(0x0);
+        emuLog::fatalError(config::SYNTAX,emuLog::EXIT_BAD_CONFIG);
     }
 
-    let diskImageName=String::from(&args[1]);
-    let comName=String::from(&args[2]);
-    let runMode=String::from(&args[3]).parse::<u8>().unwrap();
+    let theConfig=config::config::fromArgs(&args[1..]);
+    let runMode=theConfig.getRunMode();
+    if theConfig.headless || theConfig.terminal
+    {
+        // stdout is the final dump or the terminal screen
+        emuLog::setTraceEnabled(false);
+    }
 
     //
 
     let _thePIC=pic8259::pic8259::new();
-    let mut theVGA=vga::vga::new("./fonts/9x16.png","./fonts/cga8.png");
-    let theDisk=fddController::fddController::new(&diskImageName);
-    let mut theMachine=machine::machine::new(&comName,0x100000,runMode,machine::machineType::machineEGA);
+    let mut theVGA=vga::vga::new("./fonts/9x16.png","./fonts/cga8.png",theConfig.machType);
+    theVGA.compositeOutput=theConfig.compositeMonitor;
+    if theConfig.videoFile!=""
+    {
+        theVGA.startRecording(&theConfig.videoFile);
+    }
+
+    let mut theDisk=fddController::fddController::new();
+    let driveImages=[(0x00,&theConfig.fdaImage),(0x01,&theConfig.fdbImage),(0x80,&theConfig.hdaImage),(0x81,&theConfig.hdbImage)];
+    for (driveNum,imageFile) in driveImages
+    {
+        if imageFile!=""
+        {
+            theDisk.attachImage(driveNum,imageFile);
+        }
+    }
+
+    let mut theMachine=machine::machine::new(&theConfig.getProgramFile(),&theConfig.biosFile,0x100000,theConfig.ramKb,runMode,theConfig.machType);
+    if theConfig.printerFile!=""
+    {
+        theMachine.lpt1.attachFile(&theConfig.printerFile,theConfig.printerText);
+    }
+    // when booting the BIOS rewrites the data area, the machine fills it in when the boot sector starts
+    if !theMachine.biosLoaded
+    {
+        theMachine.initBDA(&theDisk,&theVGA);
+    }
+    theMachine.initVideoRom(&mut theVGA);
     let mut theCPU=x86cpu::x86cpu::new(runMode);
-    let mut theGUI=guiif::guiif::new(0x02,theCPU.cs,theCPU.ip);
+    if runMode==0
+    {
+        // the BIOS boots from the drive in DL
+        theCPU.dx=theConfig.getBootDrive() as u16;
+    }
+    let mut theLimiter=speedLimiter::speedLimiter::new(theConfig.speed);
 
+    if theConfig.headless
+    {
+        let mut theHeadless=headless::headless::new();
+        theHeadless.maxInstructions=theConfig.maxInstructions;
+        theHeadless.stopAddress=theConfig.stopAddress;
+        theHeadless.stopOnHlt=theConfig.stopOnHlt;
+        theHeadless.stopText=theConfig.stopText.clone();
+        theHeadless.pngFile=theConfig.pngFile.clone();
+        theHeadless.textFile=theConfig.textFile.clone();
+
+        let exitCode=theHeadless.run(&mut theCPU,&mut theMachine,&mut theVGA,&theDisk);
+        theMachine.lpt1.flush();
+        process::exit(exitCode);
+    }
+    else if theConfig.terminal
+    {
+        let mut theTerminal=termif::termif::new();
+        theTerminal.run(&mut theCPU,&mut theMachine,&mut theVGA,&theDisk,&mut theLimiter);
+        theVGA.stopRecording();
+        theMachine.lpt1.flush();
+        process::exit(0x0);
+    }
+
+    let mut theScaler=videoScaler::videoScaler::new();
+    theScaler.mode=theConfig.scaleMode;
+    theScaler.setZoom(theConfig.zoom);
+    theScaler.filter=theConfig.filter;
+    theScaler.showBorder=theConfig.showBorder;
+
+    let mut theGUI=guiif::guiif::new(&theVGA,theScaler,theCPU.cs,theCPU.ip);
+    theGUI.joystickA=theConfig.joystickA;
+    theGUI.joystickB=theConfig.joystickB;
+
+    // without --debug the machine starts running, the debugger comes back when the run stops
+    let mut startRunning=!theConfig.debug;
     let mut goOut=false;
     while !goOut
     {
@@ -58,12 +137,13 @@ fn main()
         theGUI.drawRegisters(&theCPU.getRegisters(),&theCPU.flags,&theCPU.totInstructions,&startTime);
         theGUI.drawMemory(&mut theVGA,&theMachine,0x3322,0x29f4,80);
         theGUI.drawVideoInfo(&mut theVGA);
-        theVGA.fbTobuf32(&mut theGUI);
-        theGUI.updateVideoWindow(&theVGA);
+        theVGA.fbTobuf32(&mut theGUI.frame);
+        theGUI.updateVideoWindow();
 
         //
 
-        let act=theGUI.getKeyAction();
+        let act=if startRunning { guiif::keyAction::actionRun } else { theGUI.getKeyAction() };
+        startRunning=false;
         if act==guiif::keyAction::actionQuit
         {
             goOut=true;
@@ -95,8 +175,8 @@ fn main()
                     theGUI.clearScreen();
                     theGUI.drawDebugArea(&mut theMachine,&mut theVGA,&mut theCPU,&theDisk);
                     theGUI.drawRegisters(&theCPU.getRegisters(),&theCPU.flags,&theCPU.totInstructions,&startTime);
-                    theVGA.fbTobuf32(&mut theGUI);
-                    theGUI.updateVideoWindow(&theVGA);
+                    theVGA.fbTobuf32(&mut theGUI.frame);
+                    theGUI.updateVideoWindow();
                 }
                 iterations+=1;
             }
@@ -163,6 +243,7 @@ fn main()
             let mut bytesRead=1;
             let mut inum:u64=0;
             let mut bailOut=false;
+            theLimiter.restart();
             while !bailOut
             {
                 let _dbgstr=theCPU.executeOne(&mut theMachine,&mut theVGA,&theDisk,false,&mut bytesRead,&0,&0);
@@ -200,13 +281,14 @@ fn main()
 
                 if inum>2000
                 {
+                    theLimiter.throttle(inum);
                     theGUI.clearScreen();
                     theGUI.drawDebugArea(&mut theMachine,&mut theVGA,&mut theCPU,&theDisk);
                     theGUI.drawRegisters(&theCPU.getRegisters(),&theCPU.flags,&theCPU.totInstructions,&startTime);
                     theGUI.drawMemory(&mut theVGA,&theMachine,0x3322,0x29f4,80);
                     theGUI.drawVideoInfo(&mut theVGA);
-                    theVGA.fbTobuf32(&mut theGUI);
-                    theGUI.updateVideoWindow(&theVGA);
+                    theVGA.fbTobuf32(&mut theGUI.frame);
+                    theGUI.updateVideoWindow();
 
                     if theGUI.checkExit()
                     {
@@ -221,4 +303,6 @@ fn main()
             }
         }
     }
+
+    theVGA.stopRecording();
 }
@@ -0,0 +1,398 @@
+/* LPT1 parallel port and printer capture - dream86 */
+
+use std::io::prelude::*;
+use std::fs::File;
+
+use crate::cp437::cp437ToChar;
+use crate::emuLog;
+
+/*
+    |7|6|5|4|3|2|1|0|  379 Status Register
+    | | | | | `-------- unused
+    | | | | `--------- 0 = printer error
+    | | | `---------- 1 = printer selected
+    | | `----------- 1 = out of paper
+    | `------------ 0 = acknowledge
+    `------------- 0 = busy
+
+    |7|6|5|4|3|2|1|0|  37A Control Register
+    | | | | | | | `---- 1 = strobe
+    | | | | | | `----- 1 = auto linefeed
+    | | | | | `------ 0 = initialize printer
+    | | | | `------- 1 = select printer
+    | | | `-------- 1 = enable IRQ7 on acknowledge
+    `-------------- unused
+*/
+
+const STATUS_READY:u8=0xdf;
+const STATUS_OFFLINE:u8=0x47;
+
+#[derive(PartialEq)]
+enum escState
+{
+    escNone,
+    escCommand,
+    escParams,
+    escUntilNul,
+}
+
+pub struct parallelPort
+{
+    pub dataReg: u8,
+    pub controlReg: u8,
+    outFile: Option<File>,
+    plainText: bool,
+    // plain text filter state
+    escMode: escState,
+    escCmd: u8,
+    escParamsNeeded: usize,
+    escParamsRead: Vec<u8>,
+    skipBytes: usize,
+    curLine: Vec<char>,
+    curColumn: usize
+}
+
+impl parallelPort
+{
+    pub fn new() -> Self
+    {
+        parallelPort
+        {
+            dataReg: 0,
+            controlReg: 0x0c,
+            outFile: None,
+            plainText: false,
+            escMode: escState::escNone,
+            escCmd: 0,
+            escParamsNeeded: 0,
+            escParamsRead: Vec::new(),
+            skipBytes: 0,
+            curLine: Vec::new(),
+            curColumn: 0
+        }
+    }
+
+    // every byte reaching the printer goes to fname; with plainText Epson/IBM Proprinter control codes are stripped
+    pub fn attachFile(&mut self,fname:&str,plainText:bool)
+    {
+        let f = match File::create(fname) {
+            Ok(f) => f,
+            Err(e) => {
+                emuLog::fatalError(&format!("Unable to create printer file {} error:{}",fname,e),emuLog::EXIT_ABORTED);
+            }
+        };
+
+        self.outFile=Some(f);
+        self.plainText=plainText;
+    }
+
+    pub fn isAttached(&self) -> bool
+    {
+        return self.outFile.is_some();
+    }
+
+    pub fn readStatus(&self) -> u8
+    {
+        if self.isAttached() { return STATUS_READY; }
+        return STATUS_OFFLINE;
+    }
+
+    // status byte as returned in AH by INT 17h
+    pub fn getBiosStatus(&self) -> u8
+    {
+        return (self.readStatus()&0xf8)^0x48;
+    }
+
+    pub fn readControl(&self) -> u8
+    {
+        return self.controlReg|0xe0;
+    }
+
+    pub fn writeData(&mut self,val:u8)
+    {
+        self.dataReg=val;
+    }
+
+    pub fn writeControl(&mut self,val:u8)
+    {
+        // byte is latched by the printer on the strobe rising edge
+        if ((self.controlReg&0x01)==0) && ((val&0x01)!=0)
+        {
+            self.printByte(self.dataReg);
+        }
+
+        // init line pulsed low
+        if ((self.controlReg&0x04)!=0) && ((val&0x04)==0)
+        {
+            self.resetPrinter();
+        }
+
+        self.controlReg=val;
+    }
+
+    pub fn resetPrinter(&mut self)
+    {
+        self.escMode=escState::escNone;
+        self.escParamsRead.clear();
+        self.skipBytes=0;
+    }
+
+    pub fn printByte(&mut self,val:u8)
+    {
+        if !self.isAttached() { return; }
+
+        if self.plainText
+        {
+            self.filterByte(val);
+        }
+        else
+        {
+            self.writeOut(&[val]);
+        }
+    }
+
+    fn writeOut(&mut self,buf:&[u8])
+    {
+        if let Some(f)=&mut self.outFile
+        {
+            f.write_all(buf).ok();
+        }
+    }
+
+    fn flushLine(&mut self)
+    {
+        let mut s:String=self.curLine.iter().collect();
+        s=String::from(s.trim_end());
+        s.push('\n');
+        self.writeOut(s.as_bytes());
+        self.curLine.clear();
+        self.curColumn=0;
+    }
+
+    // the last line of a report may not end with a LF
+    pub fn flush(&mut self)
+    {
+        if self.plainText && (self.curLine.len()>0)
+        {
+            self.flushLine();
+        }
+    }
+
+    fn putChar(&mut self,ch:char)
+    {
+        while self.curLine.len()<=self.curColumn
+        {
+            self.curLine.push(' ');
+        }
+
+        // overstrike (bold/underline done with CR) keeps the first non blank char
+        if self.curLine[self.curColumn]==' '
+        {
+            self.curLine[self.curColumn]=ch;
+        }
+        self.curColumn+=1;
+    }
+
+    fn escParamCount(cmd:u8) -> usize
+    {
+        match cmd
+        {
+            b'K' | b'L' | b'Y' | b'Z' | b'$' | b'\\' | b'X' | b'e' | b'f' => 2,
+            b'*' => 3,
+            b'3' | b'A' | b'J' | b'N' | b'Q' | b'R' | b'l' | b'S' | b'W' | b'x' | b'!' | b'-' | b't' |
+            b'U' | b'p' | b'k' | b'C' | b'_' | b'a' | b'+' | b's' | b'w' | b'I' | b'r' => 1,
+            _ => 0
+        }
+    }
+
+    fn endOfEscape(&mut self)
+    {
+        let p=&self.escParamsRead;
+
+        // bit image commands are followed by graphic data to be skipped
+        if (self.escCmd==b'K') || (self.escCmd==b'L') || (self.escCmd==b'Y') || (self.escCmd==b'Z')
+        {
+            self.skipBytes=(p[0] as usize)+((p[1] as usize)*256);
+        }
+        else if self.escCmd==b'*'
+        {
+            let bytesPerColumn=if p[0]>=32 { 3 } else { 1 };
+            self.skipBytes=((p[1] as usize)+((p[2] as usize)*256))*bytesPerColumn;
+        }
+        else if (self.escCmd==b'C') && (p[0]==0)
+        {
+            // ESC C NUL n - page length in inches
+            self.skipBytes=1;
+        }
+
+        self.escMode=escState::escNone;
+        self.escParamsRead.clear();
+    }
+
+    fn filterByte(&mut self,val:u8)
+    {
+        if self.skipBytes>0
+        {
+            self.skipBytes-=1;
+            return;
+        }
+
+        if self.escMode==escState::escUntilNul
+        {
+            if val==0 { self.escMode=escState::escNone; }
+            return;
+        }
+        else if self.escMode==escState::escParams
+        {
+            self.escParamsRead.push(val);
+            if self.escParamsRead.len()==self.escParamsNeeded
+            {
+                self.endOfEscape();
+            }
+            return;
+        }
+        else if self.escMode==escState::escCommand
+        {
+            self.escCmd=val;
+            if (val==b'B') || (val==b'D')
+            {
+                // tab stops list, NUL terminated
+                self.escMode=escState::escUntilNul;
+                return;
+            }
+
+            self.escParamsNeeded=Self::escParamCount(val);
+            if self.escParamsNeeded==0
+            {
+                self.escMode=escState::escNone;
+            }
+            else
+            {
+                self.escMode=escState::escParams;
+            }
+            return;
+        }
+
+        if val==0x1b
+        {
+            self.escMode=escState::escCommand;
+        }
+        else if val==0x0d
+        {
+            self.curColumn=0;
+        }
+        else if val==0x0a
+        {
+            self.flushLine();
+        }
+        else if val==0x0c
+        {
+            // form feed
+            if self.curLine.len()>0 { self.flushLine(); }
+            self.writeOut(&[0x0c]);
+        }
+        else if val==0x09
+        {
+            self.curColumn=((self.curColumn/8)+1)*8;
+        }
+        else if val==0x08
+        {
+            if self.curColumn>0 { self.curColumn-=1; }
+        }
+        else if (val<0x20) || (val==0x7f)
+        {
+            // SO, SI, DC2, DC4, BEL, CAN and friends only change print style
+        }
+        else
+        {
+            self.putChar(cp437ToChar(val));
+        }
+    }
+}
+
+impl Drop for parallelPort
+{
+    fn drop(&mut self)
+    {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    // prints bytes in plain text mode, returns what reached the file once the port is dropped
+    fn printText(name:&str,bytes:&[u8]) -> String
+    {
+        let path=env::temp_dir().join(format!("dream86_{}_{}",process::id(),name));
+        let fname=path.to_string_lossy().into_owned();
+        {
+            let mut lpt=parallelPort::new();
+            lpt.attachFile(&fname,true);
+            for b in bytes
+            {
+                lpt.printByte(*b);
+            }
+        }
+
+        let text=fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).ok();
+        return text;
+    }
+
+    #[test]
+    fn escapeParametersAreSkipped()
+    {
+        let text=printText("escparams.txt",b"\x1bE\x1b!\x08\x1b3\x18\x1bB\x08\x10\x00A\x1b-\x01B\n");
+        assert_eq!(text,"AB\n");
+    }
+
+    #[test]
+    fn bitImageDataIsSkipped()
+    {
+        for cmd in [b'K',b'L',b'Y',b'Z']
+        {
+            let text=printText("bitimage.txt",&[0x41,0x1b,cmd,0x03,0x00,0x41,0x42,0x43,0x42,0x0a]);
+            assert_eq!(text,"AB\n","ESC {}",cmd as char);
+        }
+
+        // ESC * m n1 n2: 1 byte per column up to mode 7, 3 bytes for the 24 pins modes
+        let text=printText("bitimage8.txt",&[0x1b,b'*',0x00,0x02,0x00,0x41,0x41,0x42,0x0a]);
+        assert_eq!(text,"B\n");
+        let text=printText("bitimage24.txt",&[0x1b,b'*',0x21,0x02,0x00,0x41,0x41,0x41,0x41,0x41,0x41,0x42,0x0a]);
+        assert_eq!(text,"B\n");
+    }
+
+    #[test]
+    fn carriageReturnOverstrikeKeepsTheText()
+    {
+        let text=printText("overstrike.txt",b"BOLD  \r____X\nA\rB\n");
+        assert_eq!(text,"BOLDX\nA\n");
+    }
+
+    #[test]
+    fn tabsGoToTheNextMultipleOf8()
+    {
+        let text=printText("tabs.txt",b"A\tB\tC\n12345678\tD\n");
+        assert_eq!(text,"A       B       C\n12345678        D\n");
+    }
+
+    #[test]
+    fn formFeedEndsTheLine()
+    {
+        let text=printText("formfeed.txt",b"PAGE 1\x0cPAGE 2\x0c");
+        assert_eq!(text,"PAGE 1\n\x0cPAGE 2\n\x0c");
+    }
+
+    #[test]
+    fn lastLineWithoutLineFeedIsKept()
+    {
+        let text=printText("lastline.txt",b"TOTAL\r\nEND OF REPORT");
+        assert_eq!(text,"TOTAL\nEND OF REPORT\n");
+    }
+}
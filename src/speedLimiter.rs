@@ -0,0 +1,47 @@
+/* keeps the emulation at a given number of instructions per second - dream86 */
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub struct speedLimiter
+{
+    // 0 runs as fast as possible
+    pub instructionsPerSecond: u64,
+    startTime: Instant,
+    // loop steps since the start, a halted cpu counts too as it lets the timer run
+    executedSteps: u64
+}
+
+impl speedLimiter
+{
+    pub fn new(instructionsPerSecond:u64) -> Self
+    {
+        speedLimiter
+        {
+            instructionsPerSecond: instructionsPerSecond,
+            startTime: Instant::now(),
+            executedSteps: 0
+        }
+    }
+
+    // to be called when the emulation (re)starts, after the debugger stopped it
+    pub fn restart(&mut self)
+    {
+        self.startTime=Instant::now();
+        self.executedSteps=0;
+    }
+
+    // steps were run since the last call, sleeps if the cpu is ahead of the wall clock
+    pub fn throttle(&mut self,steps:u64)
+    {
+        if self.instructionsPerSecond==0 { return; }
+
+        self.executedSteps+=steps;
+        let targetMicros=(self.executedSteps*1000000)/self.instructionsPerSecond;
+        let elapsedMicros=self.startTime.elapsed().as_micros() as u64;
+        if targetMicros>elapsedMicros
+        {
+            thread::sleep(Duration::from_micros(targetMicros-elapsedMicros));
+        }
+    }
+}
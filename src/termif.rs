@@ -0,0 +1,340 @@
+/* terminal interface, text modes only and no window - dream86 */
+
+use std::io::{stdout, Stdout, Write};
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use crossterm::{ExecutableCommand, QueueableCommand, terminal, cursor};
+use crossterm::style::{Attribute, Color, Print, SetAttribute, SetBackgroundColor, SetForegroundColor};
+use crossterm::event::{poll, read, Event, KeyCode, KeyEvent, KeyModifiers};
+
+use crate::machine::machine;
+use crate::vga::vga;
+use crate::x86cpu::x86cpu;
+use crate::fddController::fddController;
+use crate::cp437::cp437ToChar;
+use crate::speedLimiter::speedLimiter;
+
+// instructions run between two looks at the terminal
+const INSTRUCTIONS_PER_POLL:u32=2000;
+// the screen is redrawn at most 25 times per second, it may go through ssh
+const REFRESH_MILLIS:u128=40;
+
+// CGA colors as ANSI colors, in attribute order
+const ANSI_COLORS:[Color;16]=
+[
+    Color::Black,Color::DarkBlue,Color::DarkGreen,Color::DarkCyan,
+    Color::DarkRed,Color::DarkMagenta,Color::DarkYellow,Color::Grey,
+    Color::DarkGrey,Color::Blue,Color::Green,Color::Cyan,
+    Color::Red,Color::Magenta,Color::Yellow,Color::White
+];
+
+// US layout: unshifted char, shifted char, scancode
+const US_KEYS:[(char,char,u8);47]=
+[
+    ('1','!',0x02),('2','@',0x03),('3','#',0x04),('4','$',0x05),('5','%',0x06),('6','^',0x07),
+    ('7','&',0x08),('8','*',0x09),('9','(',0x0a),('0',')',0x0b),('-','_',0x0c),('=','+',0x0d),
+    ('q','Q',0x10),('w','W',0x11),('e','E',0x12),('r','R',0x13),('t','T',0x14),('y','Y',0x15),
+    ('u','U',0x16),('i','I',0x17),('o','O',0x18),('p','P',0x19),('[','{',0x1a),(']','}',0x1b),
+    ('a','A',0x1e),('s','S',0x1f),('d','D',0x20),('f','F',0x21),('g','G',0x22),('h','H',0x23),
+    ('j','J',0x24),('k','K',0x25),('l','L',0x26),(';',':',0x27),('\'','"',0x28),('`','~',0x29),
+    ('\\','|',0x2b),('z','Z',0x2c),('x','X',0x2d),('c','C',0x2e),('v','V',0x2f),('b','B',0x30),
+    ('n','N',0x31),('m','M',0x32),(',','<',0x33),('.','>',0x34),('/','?',0x35)
+];
+
+// raw mode and the alternate screen are on, the terminal must be given back on any exit
+static TERMINAL_TAKEN:AtomicBool=AtomicBool::new(false);
+
+// back to the normal screen and cooked mode, does nothing if the terminal wasn't taken
+pub fn restoreTerminal()
+{
+    if !TERMINAL_TAKEN.swap(false,Ordering::SeqCst) { return; }
+
+    let mut out=stdout();
+    out.queue(SetAttribute(Attribute::Reset)).ok();
+    out.queue(cursor::Show).ok();
+    out.execute(terminal::LeaveAlternateScreen).ok();
+    terminal::disable_raw_mode().ok();
+}
+
+// restores the terminal when run ends, by returning or unwinding
+struct terminalGuard;
+
+impl Drop for terminalGuard
+{
+    fn drop(&mut self)
+    {
+        restoreTerminal();
+    }
+}
+
+pub struct termif
+{
+    out: Stdout,
+    // what's on the terminal now, to redraw only the cells that changed
+    shownCells: Vec<Option<(u8,u8)>>,
+    shownCols: usize,
+    shownRows: usize,
+    shownMode: u16,
+    pendingKeys: Vec<u16>,
+    lastRefresh: Instant
+}
+
+impl termif
+{
+    pub fn new() -> Self
+    {
+        termif
+        {
+            out: stdout(),
+            shownCells: Vec::new(),
+            shownCols: 0,
+            shownRows: 0,
+            shownMode: 0xffff,
+            pendingKeys: Vec::new(),
+            lastRefresh: Instant::now()
+        }
+    }
+
+    fn charToKeystroke(c:char) -> Option<u16>
+    {
+        if c==' ' { return Some(0x3920); }
+
+        for (unshifted,shifted,scancode) in US_KEYS
+        {
+            if (c==unshifted) || (c==shifted)
+            {
+                return Some(((scancode as u16)<<8)|(c as u16));
+            }
+        }
+        return None;
+    }
+
+    // terminal key to BIOS keystroke (scancode<<8)|ascii
+    fn keyToKeystroke(keyEvent:&KeyEvent) -> Option<u16>
+    {
+        match keyEvent.code
+        {
+            KeyCode::Char(c) => {
+                let ks=Self::charToKeystroke(c)?;
+                if keyEvent.modifiers.contains(KeyModifiers::ALT)
+                {
+                    return Some(ks&0xff00);
+                }
+                else if keyEvent.modifiers.contains(KeyModifiers::CONTROL) && c.is_ascii_alphabetic()
+                {
+                    return Some((ks&0xff00)|((c.to_ascii_lowercase() as u16)&0x1f));
+                }
+                return Some(ks);
+            },
+            KeyCode::Enter => return Some(0x1c0d),
+            KeyCode::Backspace => return Some(0x0e08),
+            KeyCode::Tab => return Some(0x0f09),
+            KeyCode::BackTab => return Some(0x0f00),
+            KeyCode::Esc => return Some(0x011b),
+            KeyCode::Up => return Some(0x4800),
+            KeyCode::Down => return Some(0x5000),
+            KeyCode::Left => return Some(0x4b00),
+            KeyCode::Right => return Some(0x4d00),
+            KeyCode::Home => return Some(0x4700),
+            KeyCode::End => return Some(0x4f00),
+            KeyCode::PageUp => return Some(0x4900),
+            KeyCode::PageDown => return Some(0x5100),
+            KeyCode::Insert => return Some(0x5200),
+            KeyCode::Delete => return Some(0x5300),
+            KeyCode::F(n) => {
+                if (n>=1) && (n<=10) { return Some((0x3a+(n as u16))<<8); }
+                else if (n==11) || (n==12) { return Some((0x7a+(n as u16))<<8); }
+                return None;
+            },
+            _ => return None,
+        }
+    }
+
+    // returns false when the user asks to quit (ctrl+alt+Q)
+    fn processEvents(&mut self) -> bool
+    {
+        while poll(Duration::from_millis(0)).unwrap_or(false)
+        {
+            match read()
+            {
+                Ok(Event::Key(keyEvent)) => {
+                    if (keyEvent.code==KeyCode::Char('q')) && keyEvent.modifiers.contains(KeyModifiers::CONTROL|KeyModifiers::ALT)
+                    {
+                        return false;
+                    }
+                    if let Some(ks)=Self::keyToKeystroke(&keyEvent)
+                    {
+                        self.pendingKeys.push(ks);
+                    }
+                },
+                Ok(Event::Resize(_,_)) => self.shownMode=0xffff,
+                _ => (),
+            }
+        }
+        return true;
+    }
+
+    // the keyboard queue is read from its end, keys are fed one at a time to keep them in order
+    fn feedKeys(&mut self,pmachine:&mut machine,pcpu:&mut x86cpu)
+    {
+        if (self.pendingKeys.len()>0) && (pmachine.keyboardQueue.len()==0)
+        {
+            pmachine.addKeystroke(self.pendingKeys.remove(0));
+            pcpu.triggerHwIrq(9);
+        }
+    }
+
+    fn getCellStyle(pvga:&vga,attributes:u8) -> (Color,Color,Vec<Attribute>)
+    {
+        let mut styles:Vec<Attribute>=Vec::new();
+        let blinking=pvga.attrBlinkEnabled && ((attributes&0x80)!=0);
+        if blinking { styles.push(Attribute::SlowBlink); }
+
+        if pvga.mode==0x07
+        {
+            // MDA: only intensity, underline, reverse and invisible
+            let fgBits=attributes&0x07;
+            let bgBits=(attributes>>4)&0x07;
+            let fgCol=if (attributes&0x08)!=0 { Color::White } else { Color::Grey };
+            if (fgBits==0) && (bgBits==7)
+            {
+                return (Color::Black,Color::Grey,styles);
+            }
+            else if fgBits==0
+            {
+                return (Color::Black,Color::Black,styles);
+            }
+
+            if fgBits==1 { styles.push(Attribute::Underlined); }
+            return (fgCol,Color::Black,styles);
+        }
+
+        let bgIdx=if blinking { (attributes>>4)&0x07 } else { attributes>>4 };
+        return (ANSI_COLORS[(attributes&0x0f) as usize],ANSI_COLORS[bgIdx as usize],styles);
+    }
+
+    fn drawScreen(&mut self,pvga:&vga)
+    {
+        let out=&mut self.out;
+        let (cols,rows)=if pvga.isTextMode() { let (c,r,_h)=pvga.getTextGeometry(); (c,r) } else { (0,0) };
+
+        if (pvga.mode!=self.shownMode) || (cols!=self.shownCols) || (rows!=self.shownRows)
+        {
+            out.queue(SetAttribute(Attribute::Reset)).ok();
+            out.queue(terminal::Clear(terminal::ClearType::All)).ok();
+            self.shownMode=pvga.mode;
+            self.shownCols=cols;
+            self.shownRows=rows;
+            self.shownCells=vec![None;cols*rows];
+        }
+
+        if !pvga.isTextMode()
+        {
+            out.queue(cursor::Hide).ok();
+            out.queue(cursor::MoveTo(0,0)).ok();
+            out.queue(Print(format!("dream86: video mode {:02x} is a graphics mode, not shown in the terminal",pvga.mode))).ok();
+            out.flush().ok();
+            return;
+        }
+
+        // what doesn't fit in the terminal is cut
+        let (termCols,termRows)=terminal::size().unwrap_or((80,25));
+        let visibleCols=std::cmp::min(cols,termCols as usize);
+        let visibleRows=std::cmp::min(rows,termRows as usize);
+
+        out.queue(cursor::Hide).ok();
+        let mut curAttr:Option<u8>=None;
+        for row in 0..visibleRows
+        {
+            let mut col=0;
+            while col<visibleCols
+            {
+                let cell=pvga.getScreenCell(col,row);
+                if self.shownCells[(row*cols)+col]==Some(cell)
+                {
+                    col+=1;
+                    continue;
+                }
+
+                // a run of changed cells is printed after one cursor move
+                out.queue(cursor::MoveTo(col as u16,row as u16)).ok();
+                while col<visibleCols
+                {
+                    let (charNum,attributes)=pvga.getScreenCell(col,row);
+                    if self.shownCells[(row*cols)+col]==Some((charNum,attributes)) { break; }
+
+                    if curAttr!=Some(attributes)
+                    {
+                        let (fgCol,bgCol,styles)=Self::getCellStyle(pvga,attributes);
+                        out.queue(SetAttribute(Attribute::Reset)).ok();
+                        out.queue(SetForegroundColor(fgCol)).ok();
+                        out.queue(SetBackgroundColor(bgCol)).ok();
+                        for style in styles
+                        {
+                            out.queue(SetAttribute(style)).ok();
+                        }
+                        curAttr=Some(attributes);
+                    }
+
+                    out.queue(Print(cp437ToChar(charNum))).ok();
+                    self.shownCells[(row*cols)+col]=Some((charNum,attributes));
+                    col+=1;
+                }
+            }
+        }
+        out.queue(SetAttribute(Attribute::Reset)).ok();
+
+        if let Some((cx,cy))=pvga.getScreenCursor()
+        {
+            if (cx<visibleCols) && (cy<visibleRows)
+            {
+                out.queue(cursor::MoveTo(cx as u16,cy as u16)).ok();
+                out.queue(cursor::Show).ok();
+            }
+        }
+
+        out.flush().ok();
+    }
+
+    // runs the machine with the text screen in the terminal, until ctrl+alt+Q
+    pub fn run(&mut self,pcpu:&mut x86cpu,pmachine:&mut machine,pvga:&mut vga,pdisk:&fddController,plimiter:&mut speedLimiter)
+    {
+        terminal::enable_raw_mode().ok();
+        self.out.execute(terminal::EnterAlternateScreen).ok();
+        TERMINAL_TAKEN.store(true,Ordering::SeqCst);
+        let _guard=terminalGuard;
+
+        // a panic message printed in the alternate screen would vanish with it
+        let defaultHook=panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restoreTerminal();
+            defaultHook(info);
+        }));
+
+        let mut bytesRead=1;
+        plimiter.restart();
+        loop
+        {
+            for _i in 0..INSTRUCTIONS_PER_POLL
+            {
+                pcpu.executeOne(pmachine,pvga,pdisk,false,&mut bytesRead,&0,&0);
+                pmachine.update(pcpu);
+                pvga.update();
+            }
+            plimiter.throttle(INSTRUCTIONS_PER_POLL as u64);
+
+            if !self.processEvents()
+            {
+                break;
+            }
+            self.feedKeys(pmachine,pcpu);
+
+            if self.lastRefresh.elapsed().as_millis()>=REFRESH_MILLIS
+            {
+                self.drawScreen(pvga);
+                self.lastRefresh=Instant::now();
+            }
+        }
+    }
+}
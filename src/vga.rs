@@ -1,9 +1,13 @@
 /* the VGA - dream86 */
 
-use std::process;
+use std::fs;
 
-use crate::guiif::guiif;
 use crate::machine::machine;
+use crate::machine::machineType;
+use crate::videoFrame::videoFrame;
+use crate::videoRecorder::videoRecorder;
+use crate::cp437::cp437ToChar;
+use crate::emuLog;
 
 pub struct vga
 {
@@ -11,17 +15,23 @@ pub struct vga
     pub framebuffer: Vec<u8>,
     pub cgaFramebuffer: Vec<u8>,
     pub tempFramebuf: Vec<u32>,
-    pub font9x16data:Vec<Vec<u8>>,
     pub font9x16width:u32,
     pub font9x16height:u32,
+    pub romFont8x16:Vec<u8>,
+    pub romFont8x14:Vec<u8>,
+    pub romFont8x8:Vec<u8>,
     pub font8x8data:Vec<Vec<u32>>,
     pub font8x8width:u32,
     pub font8x8height:u32,
-    pub cursorx:usize,
-    pub cursory:usize,
+    pub pageCursors:Vec<(usize,usize)>,
+    pub activePage:usize,
     pub vgaPalette: Vec<u32>,
     pub vgaPaletteCurColor: u8,
     pub vgaPaletteIndexRGB: u8,
+    pub vgaPaletteReadColor: u8,
+    pub vgaDacReadMode: bool,
+    pub vgaDacLatch: Vec<u8>,
+    pub vgaPixelMask: u8,
     pub egaRegister3ceSelected: u8,
     pub egaRegister3cfValues: Vec<u8>,
     pub egaRegister3c4Selected: u8,
@@ -29,16 +39,128 @@ pub struct vga
     pub egaRegister3b4Selected: u8,
     pub egaRegister3b5Values: Vec<u8>,
     pub egaDataLatch: Vec<u8>,
+    pub vgaRegister3c0Selected: u8,
+    pub vgaRegister3c0Values: Vec<u8>,
+    pub vgaRegister3c0IsData: bool,
     pub scanlineCounter: u32,
-    pub cgaPaletteSelected: u8
+    // emulated frames since power on, the blinking follows them
+    pub frameCounter: u64,
+    pub cgaModeReg: u8,
+    pub cgaColorReg: u8,
+    pub attrBlinkEnabled: bool,
+    pub cardType: machineType,
+    pub herculesModeReg: u8,
+    pub herculesConfigReg: u8,
+    pub vesaWindowPos: usize,
+    pub compositeOutput: bool,
+    pub textScanlines: u16,
+    pub graphicsCharHeight: usize,
+    pub graphicsTextRows: u16,
+    pub recorder: videoRecorder
+}
+
+// 1Mb of video memory, needed by the SVGA modes
+const VGA_MEMORY_SIZE:usize=0x100000;
+
+// the 16 RGBI colors of the CGA
+const CGA_PALETTE:[u32;16]=
+[
+    0x000000,0x0000aa,0x00aa00,0x00aaaa,0xaa0000,0xaa00aa,0xaa5500,0xaaaaaa,
+    0x555555,0x5555ff,0x55ff55,0x55ffff,0xff5555,0xff55ff,0xffff55,0xffffff
+];
+
+// CGA composite output: the chroma of colors 1-6 is a square wave, high for half a color clock
+// starting at this phase (in half dots, 8 per clock). Black has no chroma, white is always high
+const CGA_CHROMA_PHASE:[usize;8]=[0,4,6,5,1,2,0,0];
+// NTSC decoding, tuned so that white dots in 640x200 give the usual 16 artifact colors
+const CGA_COMPOSITE_HUE:f32=4.887; // radians
+const CGA_COMPOSITE_SATURATION:f32=0.86;
+const CGA_COMPOSITE_GAMMA:f32=1.13;
+
+// VGA BIOS DAC for mode 13h: the 16 colors, 16 grays, then hue/saturation/value ramps
+const VGA_DEFAULT_PALETTE:[u32;256]=
+[
+    0x000000,0x0000aa,0x00aa00,0x00aaaa,0xaa0000,0xaa00aa,0xaa5500,0xaaaaaa,
+    0x555555,0x5555ff,0x55ff55,0x55ffff,0xff5555,0xff55ff,0xffff55,0xffffff,
+    0x000000,0x141414,0x202020,0x2c2c2c,0x383838,0x454545,0x515151,0x616161,
+    0x717171,0x828282,0x929292,0xa2a2a2,0xb6b6b6,0xcbcbcb,0xe3e3e3,0xffffff,
+    0x0000ff,0x4100ff,0x7d00ff,0xbe00ff,0xff00ff,0xff00be,0xff007d,0xff0041,
+    0xff0000,0xff4100,0xff7d00,0xffbe00,0xffff00,0xbeff00,0x7dff00,0x41ff00,
+    0x00ff00,0x00ff41,0x00ff7d,0x00ffbe,0x00ffff,0x00beff,0x007dff,0x0041ff,
+    0x7d7dff,0x9e7dff,0xbe7dff,0xdf7dff,0xff7dff,0xff7ddf,0xff7dbe,0xff7d9e,
+    0xff7d7d,0xff9e7d,0xffbe7d,0xffdf7d,0xffff7d,0xdfff7d,0xbeff7d,0x9eff7d,
+    0x7dff7d,0x7dff9e,0x7dffbe,0x7dffdf,0x7dffff,0x7ddfff,0x7dbeff,0x7d9eff,
+    0xb6b6ff,0xc7b6ff,0xdbb6ff,0xebb6ff,0xffb6ff,0xffb6eb,0xffb6db,0xffb6c7,
+    0xffb6b6,0xffc7b6,0xffdbb6,0xffebb6,0xffffb6,0xebffb6,0xdbffb6,0xc7ffb6,
+    0xb6ffb6,0xb6ffc7,0xb6ffdb,0xb6ffeb,0xb6ffff,0xb6ebff,0xb6dbff,0xb6c7ff,
+    0x000071,0x1c0071,0x380071,0x550071,0x710071,0x710055,0x710038,0x71001c,
+    0x710000,0x711c00,0x713800,0x715500,0x717100,0x557100,0x387100,0x1c7100,
+    0x007100,0x00711c,0x007138,0x007155,0x007171,0x005571,0x003871,0x001c71,
+    0x383871,0x453871,0x553871,0x613871,0x713871,0x713861,0x713855,0x713845,
+    0x713838,0x714538,0x715538,0x716138,0x717138,0x617138,0x557138,0x457138,
+    0x387138,0x387145,0x387155,0x387161,0x387171,0x386171,0x385571,0x384571,
+    0x515171,0x595171,0x615171,0x695171,0x715171,0x715169,0x715161,0x715159,
+    0x715151,0x715951,0x716151,0x716951,0x717151,0x697151,0x617151,0x597151,
+    0x517151,0x517159,0x517161,0x517169,0x517171,0x516971,0x516171,0x515971,
+    0x000041,0x100041,0x200041,0x300041,0x410041,0x410030,0x410020,0x410010,
+    0x410000,0x411000,0x412000,0x413000,0x414100,0x304100,0x204100,0x104100,
+    0x004100,0x004110,0x004120,0x004130,0x004141,0x003041,0x002041,0x001041,
+    0x202041,0x282041,0x302041,0x382041,0x412041,0x412038,0x412030,0x412028,
+    0x412020,0x412820,0x413020,0x413820,0x414120,0x384120,0x304120,0x284120,
+    0x204120,0x204128,0x204130,0x204138,0x204141,0x203841,0x203041,0x202841,
+    0x2c2c41,0x302c41,0x342c41,0x3c2c41,0x412c41,0x412c3c,0x412c34,0x412c30,
+    0x412c2c,0x41302c,0x41342c,0x413c2c,0x41412c,0x3c412c,0x34412c,0x30412c,
+    0x2c412c,0x2c4130,0x2c4134,0x2c413c,0x2c4141,0x2c3c41,0x2c3441,0x2c3041,
+    0x000000,0x000000,0x000000,0x000000,0x000000,0x000000,0x000000,0x000000
+];
+
+// MC6845 registers 00h-0fh as the IBM CGA and MDA BIOS program them
+const CGA_CRTC_40X25:[u8;16]=[0x38,0x28,0x2d,0x0a,0x1f,0x06,0x19,0x1c,0x02,0x07,0x06,0x07,0x00,0x00,0x00,0x00];
+const CGA_CRTC_80X25:[u8;16]=[0x71,0x50,0x5a,0x0a,0x1f,0x06,0x19,0x19,0x02,0x07,0x06,0x07,0x00,0x00,0x00,0x00];
+const CGA_CRTC_GRAPHICS:[u8;16]=[0x38,0x28,0x2d,0x0a,0x7f,0x06,0x64,0x70,0x02,0x01,0x06,0x07,0x00,0x00,0x00,0x00];
+const MDA_CRTC_80X25:[u8;16]=[0x61,0x50,0x52,0x0f,0x19,0x06,0x19,0x19,0x02,0x0d,0x0b,0x0c,0x00,0x00,0x00,0x00];
+
+// VGA memory is 4 planes of 64k, stored interleaved: byte n of plane p is at framebuffer[(n*4)+p]
+// like this the chain-4 (mode 13h) view of the memory is linear
+fn planeIndex(planeOffs:usize,plane:usize) -> usize
+{
+    return ((planeOffs&0xffff)<<2)|plane;
 }
 
 impl vga
 {
-    pub fn setVideomode(&mut self,videomodeNum:u16)
+    pub fn isMonoAdapter(&self) -> bool
     {
-        self.cursorx=0;
-        self.cursory=0;
+        return (self.cardType==machineType::machineMDA) || (self.cardType==machineType::machineHercules);
+    }
+
+    pub fn setVideomode(&mut self,reqVideomode:u16)
+    {
+        // a mono card only knows mode 7, plus the Hercules graphics page as mode 8 (like MSHERC does)
+        let mut videomodeNum=reqVideomode;
+        if self.isMonoAdapter() && (videomodeNum!=0x07) && 
+           !((videomodeNum==0x08) && (self.cardType==machineType::machineHercules))
+        {
+            videomodeNum=0x07;
+        }
+
+        for page in 0..self.pageCursors.len()
+        {
+            self.pageCursors[page]=(0,0);
+        }
+        self.activePage=0;
+        if videomodeNum!=0x08
+        {
+            self.loadModeRegisters(videomodeNum);
+        }
+
+        // CGA mode control and color select, as the BIOS sets them
+        if videomodeNum<=0x06
+        {
+            let cgaModeValues=[0x2c,0x28,0x2d,0x29,0x2a,0x2e,0x1e];
+            self.cgaModeReg=cgaModeValues[videomodeNum as usize];
+            self.cgaColorReg=if videomodeNum==0x06 { 0x3f } else if videomodeNum>=0x04 { 0x30 } else { 0x00 };
+        }
 
         if videomodeNum==0x13
         {
@@ -87,6 +209,27 @@ impl vga
                 self.cgaFramebuffer[idx]=0;
             }
         }
+        else if videomodeNum==0x07
+        {
+            // MDA 80x25 textmode 9x14, 720x350
+            self.mode=0x07;
+            self.herculesModeReg=0x28;
+            for idx in 0..0x1000
+            {
+                self.cgaFramebuffer[idx]=if (idx%2)==0 { 0x20 } else { 0x07 };
+            }
+        }
+        else if videomodeNum==0x08
+        {
+            // Hercules 720x348 graphics, page 0
+            self.mode=0x08;
+            self.herculesConfigReg|=0x01;
+            self.herculesModeReg=0x0a;
+            for idx in 0..0x8000
+            {
+                self.cgaFramebuffer[idx]=0;
+            }
+        }
         else if videomodeNum==0x0d
         {
             // EGA 320x200x16
@@ -96,251 +239,1136 @@ impl vga
                 self.framebuffer[idx]=0;
             }
         }
-        else if videomodeNum==0x10
+        else if (videomodeNum==0x0e) || (videomodeNum==0x0f) || (videomodeNum==0x10) || 
+                (videomodeNum==0x11) || (videomodeNum==0x12)
+        {
+            // EGA 640x200x16, 640x350 mono, 640x350x16
+            // VGA 640x480x2, 640x480x16
+            self.mode=videomodeNum;
+            for idx in 0..self.framebuffer.len()
+            {
+                self.framebuffer[idx]=0;
+            }
+        }
+        else
+        {
+            emuLog::fatalError(&format!("Bailing out: vga::cannot switch to mode {:02x}",videomodeNum),emuLog::EXIT_ABORTED);
+        }
+    }
+
+    // VBE 1.2 256 colors modes
+    pub fn getVesaResolution(vesaMode:u16) -> Option<(u32,u32)>
+    {
+        if vesaMode==0x101 { return Some((640,480)); }
+        else if vesaMode==0x103 { return Some((800,600)); }
+        return None;
+    }
+
+    pub fn isVesaMode(&self) -> bool
+    {
+        return self.mode>=0x100;
+    }
+
+    // INT 10h,4F02 - returns false if the mode is not available
+    pub fn setVesaMode(&mut self,vesaMode:u16,clearMemory:bool) -> bool
+    {
+        if self.isMonoAdapter() || vga::getVesaResolution(vesaMode).is_none()
+        {
+            return false;
+        }
+
+        for page in 0..self.pageCursors.len()
+        {
+            self.pageCursors[page]=(0,0);
+        }
+        self.activePage=0;
+        self.loadModeRegisters(0x13);
+
+        self.mode=vesaMode;
+        self.vesaWindowPos=0;
+        if clearMemory
         {
-            // EGA 640x350x16
-            self.mode=0x10;
             for idx in 0..self.framebuffer.len()
             {
                 self.framebuffer[idx]=0;
             }
         }
+
+        return true;
+    }
+
+    // INT 10h,4F05 - window A position, in 64k granularity units
+    pub fn setVesaWindow(&mut self,windowPos:usize) -> bool
+    {
+        if (windowPos<<16)>=VGA_MEMORY_SIZE { return false; }
+        self.vesaWindowPos=windowPos;
+        return true;
+    }
+
+    // a000:0000-a000:ffff is a window on the linear video memory in the SVGA modes
+    fn getVesaWindowIndex(&self,addr:i64) -> Option<usize>
+    {
+        if self.isVesaMode() && (addr>=0xa0000) && (addr<=0xaffff)
+        {
+            return Some(((self.vesaWindowPos<<16)+((addr-0xa0000) as usize))%VGA_MEMORY_SIZE);
+        }
+
+        return None;
+    }
+
+    // VGA BIOS parameter table: sequencer 01h-04h, CRTC 00h-18h, attribute controller 10h-14h, graphics controller 00h-08h
+    fn getModeParameters(videomodeNum:u16) -> ([u8;4],[u8;25],[u8;5],[u8;9])
+    {
+        if (videomodeNum==0x00) || (videomodeNum==0x01)
+        {
+            return ([0x08,0x03,0x00,0x02],
+                    [0x2d,0x27,0x28,0x90,0x2b,0xa0,0xbf,0x1f,0x00,0x4f,0x0d,0x0e,0x00,0x00,0x00,0x00,
+                     0x9c,0x8e,0x8f,0x14,0x1f,0x96,0xb9,0xa3,0xff],
+                    [0x0c,0x00,0x0f,0x08,0x00],
+                    [0x00,0x00,0x00,0x00,0x00,0x10,0x0e,0x0f,0xff]);
+        }
+        else if (videomodeNum==0x04) || (videomodeNum==0x05)
+        {
+            return ([0x09,0x03,0x00,0x02],
+                    [0x2d,0x27,0x28,0x90,0x2b,0x80,0xbf,0x1f,0x00,0xc1,0x00,0x00,0x00,0x00,0x00,0x00,
+                     0x9c,0x8e,0x8f,0x14,0x00,0x96,0xb9,0xa2,0xff],
+                    [0x01,0x00,0x03,0x00,0x00],
+                    [0x00,0x00,0x00,0x00,0x00,0x30,0x0f,0x0f,0xff]);
+        }
+        else if videomodeNum==0x06
+        {
+            return ([0x01,0x01,0x00,0x06],
+                    [0x5f,0x4f,0x50,0x82,0x54,0x80,0xbf,0x1f,0x00,0xc1,0x00,0x00,0x00,0x00,0x00,0x00,
+                     0x9c,0x8e,0x8f,0x28,0x00,0x96,0xb9,0xc2,0xff],
+                    [0x01,0x00,0x01,0x00,0x00],
+                    [0x00,0x00,0x00,0x00,0x00,0x00,0x0d,0x0f,0xff]);
+        }
+        else if videomodeNum==0x07
+        {
+            return ([0x00,0x03,0x00,0x02],
+                    [0x5f,0x4f,0x50,0x82,0x55,0x81,0xbf,0x1f,0x00,0x4d,0x0b,0x0c,0x00,0x00,0x00,0x00,
+                     0x83,0x85,0x5d,0x28,0x0d,0x63,0xba,0xa3,0xff],
+                    [0x0e,0x00,0x0f,0x08,0x00],
+                    [0x00,0x00,0x00,0x00,0x00,0x10,0x0a,0x0f,0xff]);
+        }
+        else if videomodeNum==0x0d
+        {
+            return ([0x09,0x0f,0x00,0x06],
+                    [0x2d,0x27,0x28,0x90,0x2b,0x80,0xbf,0x1f,0x00,0xc0,0x00,0x00,0x00,0x00,0x00,0x00,
+                     0x9c,0x8e,0x8f,0x14,0x00,0x96,0xb9,0xe3,0xff],
+                    [0x01,0x00,0x0f,0x00,0x00],
+                    [0x00,0x00,0x00,0x00,0x00,0x00,0x05,0x0f,0xff]);
+        }
+        else if videomodeNum==0x0e
+        {
+            return ([0x01,0x0f,0x00,0x06],
+                    [0x5f,0x4f,0x50,0x82,0x54,0x80,0xbf,0x1f,0x00,0xc0,0x00,0x00,0x00,0x00,0x00,0x00,
+                     0x9c,0x8e,0x8f,0x28,0x00,0x96,0xb9,0xe3,0xff],
+                    [0x01,0x00,0x0f,0x00,0x00],
+                    [0x00,0x00,0x00,0x00,0x00,0x00,0x05,0x0f,0xff]);
+        }
+        else if videomodeNum==0x0f
+        {
+            // mono graphics: plane 0 is video, plane 2 is intensity
+            return ([0x01,0x0f,0x00,0x06],
+                    [0x5f,0x4f,0x50,0x82,0x54,0x80,0xbf,0x1f,0x00,0x40,0x00,0x00,0x00,0x00,0x00,0x00,
+                     0x83,0x85,0x5d,0x28,0x0f,0x63,0xba,0xe3,0xff],
+                    [0x0b,0x00,0x05,0x00,0x00],
+                    [0x00,0x00,0x00,0x00,0x00,0x00,0x05,0x05,0xff]);
+        }
+        else if (videomodeNum==0x11) || (videomodeNum==0x12)
+        {
+            return ([0x01,0x0f,0x00,0x06],
+                    [0x5f,0x4f,0x50,0x82,0x54,0x80,0x0b,0x3e,0x00,0x40,0x00,0x00,0x00,0x00,0x00,0x00,
+                     0xea,0x8c,0xdf,0x28,0x00,0xe7,0x04,0xe3,0xff],
+                    [0x01,0x00,0x0f,0x00,0x00],
+                    [0x00,0x00,0x00,0x00,0x00,0x00,0x05,0x0f,0xff]);
+        }
+        else if videomodeNum==0x10
+        {
+            return ([0x01,0x0f,0x00,0x06],
+                    [0x5f,0x4f,0x50,0x82,0x54,0x80,0xbf,0x1f,0x00,0x40,0x00,0x00,0x00,0x00,0x00,0x00,
+                     0x83,0x85,0x5d,0x28,0x0f,0x63,0xba,0xe3,0xff],
+                    [0x01,0x00,0x0f,0x00,0x00],
+                    [0x00,0x00,0x00,0x00,0x00,0x00,0x05,0x0f,0xff]);
+        }
+        else if videomodeNum==0x13
+        {
+            return ([0x01,0x0f,0x00,0x0e],
+                    [0x5f,0x4f,0x50,0x82,0x54,0x80,0xbf,0x1f,0x00,0x41,0x00,0x00,0x00,0x00,0x00,0x00,
+                     0x9c,0x8e,0x8f,0x28,0x40,0x96,0xb9,0xa3,0xff],
+                    [0x41,0x00,0x0f,0x00,0x00],
+                    [0x00,0x00,0x00,0x00,0x00,0x40,0x05,0x0f,0xff]);
+        }
+
+        // 80x25 text
+        return ([0x00,0x03,0x00,0x02],
+                [0x5f,0x4f,0x50,0x82,0x55,0x81,0xbf,0x1f,0x00,0x4f,0x0d,0x0e,0x00,0x00,0x00,0x00,
+                 0x9c,0x8e,0x8f,0x28,0x1f,0x96,0xb9,0xa3,0xff],
+                [0x0c,0x00,0x0f,0x08,0x00],
+                [0x00,0x00,0x00,0x00,0x00,0x10,0x0e,0x0f,0xff]);
+    }
+
+    // programs the sequencer, CRTC, attribute and graphics controllers like the BIOS does on a mode set
+    fn loadModeRegisters(&mut self,videomodeNum:u16)
+    {
+        let (seqRegs,crtcRegs,attrRegs,gcRegs)=vga::getModeParameters(videomodeNum);
+
+        self.egaRegister3c5Values[0]=0x03;
+        for idx in 0..4 { self.egaRegister3c5Values[idx+1]=seqRegs[idx]; }
+        for idx in 0..25 { self.egaRegister3b5Values[idx]=crtcRegs[idx]; }
+        for idx in 0..9 { self.egaRegister3cfValues[idx]=gcRegs[idx]; }
+
+        if self.hasMc6845() && (videomodeNum<=0x07)
+        {
+            // CGA, MDA and Hercules have a plain 6845, there's nothing after register 0fh
+            let crtc6845=if videomodeNum==0x07 { MDA_CRTC_80X25 } 
+                         else if videomodeNum>=0x04 { CGA_CRTC_GRAPHICS } 
+                         else if videomodeNum>=0x02 { CGA_CRTC_80X25 } 
+                         else { CGA_CRTC_40X25 };
+            for idx in 0..25 { self.egaRegister3b5Values[idx]=if idx<16 { crtc6845[idx] } else { 0 }; }
+        }
+        else if (videomodeNum<=0x03) && (self.textScanlines==350)
+        {
+            // 350 lines text (INT 10h,12 BL=30h), with 14 scanlines chars
+            let crtc350:[(usize,u8);8]=[(0x09,0x4d),(0x0a,0x0b),(0x0b,0x0c),(0x10,0x83),(0x11,0x85),(0x12,0x5d),(0x15,0x63),(0x16,0xba)];
+            for (reg,val) in crtc350 { self.egaRegister3b5Values[reg]=val; }
+        }
+
+        // the BIOS loads the ROM font in the character generator RAM on text mode sets
+        if (!self.hasMc6845()) && ((videomodeNum<=0x03) || (videomodeNum==0x07))
+        {
+            if (videomodeNum==0x07) || (self.textScanlines==350)
+            {
+                let romFont=self.romFont8x14.clone();
+                self.loadFontGlyphs(&romFont,14,0,256,0);
+            }
+            else
+            {
+                let romFont=self.romFont8x16.clone();
+                self.loadFontGlyphs(&romFont,16,0,256,0);
+            }
+        }
+        self.graphicsCharHeight=if (videomodeNum==0x0f) || (videomodeNum==0x10) { 14 } 
+                                else if (videomodeNum==0x11) || (videomodeNum==0x12) { 16 } 
+                                else { 8 };
+        self.graphicsTextRows=if (videomodeNum==0x11) || (videomodeNum==0x12) { 30 } else { 25 };
+
+        // palette registers: the 200 lines modes index the CGA colors of the DAC, the others the 64 EGA colors
+        let egaPalette:[u8;16]=[0x00,0x01,0x02,0x03,0x04,0x05,0x14,0x07,0x38,0x39,0x3a,0x3b,0x3c,0x3d,0x3e,0x3f];
+        let cgaPalette:[u8;16]=[0x00,0x01,0x02,0x03,0x04,0x05,0x06,0x07,0x10,0x11,0x12,0x13,0x14,0x15,0x16,0x17];
+        for idx in 0..16
+        {
+            if videomodeNum==0x13
+            {
+                self.vgaRegister3c0Values[idx]=idx as u8;
+            }
+            else if (videomodeNum<=0x06) || (videomodeNum==0x0d) || (videomodeNum==0x0e)
+            {
+                self.vgaRegister3c0Values[idx]=cgaPalette[idx];
+            }
+            else if (videomodeNum==0x0f) || (videomodeNum==0x11)
+            {
+                // mono modes: bit 0 is the pixel, in mode 0fh bit 2 makes it bright
+                let mut palEntry:u8=0x00;
+                if (idx&0x01)!=0 { palEntry=if videomodeNum==0x11 { 0x3f } else { 0x07 }; }
+                if (idx&0x05)==0x05 { palEntry=0x3f; }
+                self.vgaRegister3c0Values[idx]=palEntry;
+            }
+            else
+            {
+                self.vgaRegister3c0Values[idx]=egaPalette[idx];
+            }
+        }
+        self.loadDefaultDac(videomodeNum);
+        for idx in 0..5 { self.vgaRegister3c0Values[0x10+idx]=attrRegs[idx]; }
+        self.vgaRegister3c0IsData=false;
+
+        if (videomodeNum<=0x03) || (videomodeNum==0x07)
+        {
+            self.attrBlinkEnabled=(attrRegs[0]&0x08)!=0;
+        }
+    }
+
+    // the CGA, MDA and Hercules CRTC is a Motorola 6845, the EGA/VGA one has its own registers layout
+    fn hasMc6845(&self) -> bool
+    {
+        return self.cardType!=machineType::machineEGA;
+    }
+
+    // text screen as programmed in the CRTC: (columns, rows, scanlines per char row)
+    // the 6845 counts displayed chars (R1) and char rows (R6), the VGA counts char clocks (01h) and display end scanlines (12h)
+    pub fn getTextGeometry(&self) -> (usize,usize,usize)
+    {
+        let crtc=&self.egaRegister3b5Values;
+        let charHeight=((crtc[0x09]&0x1f) as usize)+1;
+
+        let cols;
+        let rows;
+        if self.hasMc6845()
+        {
+            cols=crtc[0x01] as usize;
+            rows=(crtc[0x06]&0x7f) as usize;
+        }
+        else
+        {
+            let vertDisplayEnd=(crtc[0x12] as usize)|(((crtc[0x07] as usize)&0x02)<<7)|(((crtc[0x07] as usize)&0x40)<<3);
+            cols=(crtc[0x01] as usize)+1;
+            rows=(vertDisplayEnd+1)/charHeight;
+        }
+
+        // don't follow half programmed CRTCs, the screen has to fit the 32k text buffer and the output window
+        let pixelsHigh=rows*charHeight*self.getTextLineScale();
+        if (cols==0) || (cols>132) || (rows==0) || ((cols*rows*2)>0x8000) || (pixelsHigh>800) ||
+           ((cols*9*pixelsHigh)>self.tempFramebuf.len())
+        {
+            let defaultCols=if self.mode<=0x01 { 40 } else { 80 };
+            return (defaultCols,25,if self.isMonoAdapter() { 14 } else { 16/self.getTextLineScale() });
+        }
+
+        return (cols,rows,charHeight);
+    }
+
+    // the CGA shows its 200 lines text double scanned, with the 16 scanlines font
+    fn getTextLineScale(&self) -> usize
+    {
+        if self.cardType==machineType::machineCGA { return 2; }
+        return 1;
+    }
+
+    // text screen size in pixels, chars are 9 dots wide
+    pub fn getTextResolution(&self) -> (u32,u32)
+    {
+        let (cols,rows,charHeight)=self.getTextGeometry();
+        return ((cols*9) as u32,(rows*charHeight*self.getTextLineScale()) as u32);
+    }
+
+    // plane 2 offset of font block 0-7: blocks are 8k apart, in the order 0k,16k,32k,48k,8k,24k,40k,56k
+    fn getFontBlockBase(block:u8) -> usize
+    {
+        return (((block&0x03) as usize)*0x4000)+((((block>>2)&0x01) as usize)*0x2000);
+    }
+
+    // sequencer 03h: char map A (bits 5,3,2) is used when attribute bit 3 is set, map B (bits 4,1,0) when it's clear
+    fn getCharMapBase(&self,attributes:u8) -> usize
+    {
+        let charMapSelect=self.egaRegister3c5Values[3];
+        let block;
+        if (attributes&0x08)!=0
+        {
+            block=((charMapSelect>>2)&0x03)|((charMapSelect>>3)&0x04);
+        }
+        else
+        {
+            block=(charMapSelect&0x03)|((charMapSelect>>2)&0x04);
+        }
+        return vga::getFontBlockBase(block);
+    }
+
+    // copies count glyphs of charHeight bytes to the character generator RAM (plane 2), starting from char firstChar
+    // every char has 32 scanlines there, the ones under charHeight are blanked
+    pub fn loadFontGlyphs(&mut self,glyphs:&[u8],charHeight:usize,firstChar:usize,count:usize,block:u8)
+    {
+        let blockBase=vga::getFontBlockBase(block&0x07);
+        for idx in 0..count
+        {
+            let charOffs=blockBase+(((firstChar+idx)&0xff)*32);
+            for line in 0..32
+            {
+                let glyphLine=if line<charHeight { glyphs.get((idx*charHeight)+line).copied().unwrap_or(0) } else { 0 };
+                self.framebuffer[planeIndex(charOffs+line,2)]=glyphLine;
+            }
+        }
+    }
+
+    // INT 10h,11 AL=10h/11h/12h/14h - the CRTC char height follows the loaded font, and so does the number of rows
+    pub fn setTextCharHeight(&mut self,charHeight:usize)
+    {
+        if (!self.isTextMode()) || self.hasMc6845() || (charHeight==0) || (charHeight>32) { return; }
+
+        let cursorStart=if charHeight>8 { charHeight-3 } else { charHeight.saturating_sub(2) };
+        self.egaRegister3b5Values[0x09]=(self.egaRegister3b5Values[0x09]&0xe0)|((charHeight-1) as u8);
+        self.egaRegister3b5Values[0x0a]=cursorStart as u8;
+        self.egaRegister3b5Values[0x0b]=std::cmp::min(cursorStart+1,charHeight-1) as u8;
+        self.updateCrtcCursor();
+    }
+
+    // INT 10h,11 AL=01h/02h/04h - loads the 8x14, 8x8 or 8x16 ROM font in font block
+    // AL=11h/12h/14h also reprograms the char height, so the rows become 28, 50 (43 with 350 lines) or 25
+    pub fn loadRomTextFont(&mut self,fontHeight:usize,block:u8,recalcRows:bool)
+    {
+        if self.hasMc6845() { return; }
+
+        let romFont=if fontHeight==8 { self.romFont8x8.clone() } 
+                    else if fontHeight==14 { self.romFont8x14.clone() } 
+                    else { self.romFont8x16.clone() };
+        self.loadFontGlyphs(&romFont,fontHeight,0,256,block);
+
+        if recalcRows
+        {
+            self.setTextCharHeight(fontHeight);
+        }
+    }
+
+    // INT 10h,11 AL=03h - font blocks used by the attribute bit 3
+    pub fn setFontBlockSpecifier(&mut self,val:u8)
+    {
+        self.egaRegister3c5Values[3]=val&0x3f;
+    }
+
+    // INT 10h,11 AL=22h/23h/24h - ROM font for the graphics modes
+    // rowsCode (BL) is 1 for 14 rows, 2 for 25, 3 for 43 and 0 to take userRows (DL)
+    pub fn setGraphicsFont(&mut self,charHeight:usize,rowsCode:u8,userRows:u8)
+    {
+        if self.isTextMode() { return; }
+
+        self.graphicsCharHeight=charHeight;
+        if rowsCode==0 { self.graphicsTextRows=std::cmp::max(userRows,1) as u16; }
+        else if rowsCode==1 { self.graphicsTextRows=14; }
+        else if rowsCode==3 { self.graphicsTextRows=43; }
+        else { self.graphicsTextRows=25; }
+    }
+
+    // INT 10h,12 BL=30h - scanlines of the next text mode set, 350 or 400 (the 200 lines CGA emulation is not there)
+    pub fn selectTextScanlines(&mut self,selector:u8) -> bool
+    {
+        if self.hasMc6845() { return false; }
+
+        if selector==1 { self.textScanlines=350; }
+        else if selector==2 { self.textScanlines=400; }
+        else { return false; }
+        return true;
+    }
+
+    // char height in scanlines, as the BIOS keeps it at 40:85
+    pub fn getCharHeight(&self) -> u16
+    {
+        if self.isTextMode() { return self.getTextGeometry().2 as u16; }
+        return self.graphicsCharHeight as u16;
+    }
+
+    // the VGA BIOS DAC setup: the 256 colors palette for mode 13h, else the 64 EGA colors (rgbRGB)
+    // or, for the 200 lines modes, the 16 CGA colors repeated with bit 4 as intensity
+    fn loadDefaultDac(&mut self,videomodeNum:u16)
+    {
+        self.vgaPixelMask=0xff;
+        for idx in 0..256
+        {
+            if (videomodeNum==0x13) || (idx>=64)
+            {
+                self.vgaPalette[idx]=VGA_DEFAULT_PALETTE[idx];
+            }
+            else if (videomodeNum<=0x06) || (videomodeNum==0x0d) || (videomodeNum==0x0e)
+            {
+                self.vgaPalette[idx]=CGA_PALETTE[(idx&0x07)|((idx>>1)&0x08)];
+            }
+            else
+            {
+                let red=(((idx>>2)&0x01)*0xaa)+(((idx>>5)&0x01)*0x55);
+                let green=(((idx>>1)&0x01)*0xaa)+(((idx>>4)&0x01)*0x55);
+                let blue=((idx&0x01)*0xaa)+(((idx>>3)&0x01)*0x55);
+                self.vgaPalette[idx]=((red<<16)|(green<<8)|blue) as u32;
+            }
+        }
+    }
+
+    // DAC components are 6 bits, expanded to 8 repeating the top bits (3fh is ffh)
+    fn expandDacComponent(val:u8) -> u32
+    {
+        let component=(val&0x3f) as u32;
+        return (component<<2)|(component>>4);
+    }
+
+    // (red,green,blue) of a DAC entry, 6 bits each
+    pub fn getDacEntry(&self,idx:u8) -> (u8,u8,u8)
+    {
+        let color=self.vgaPalette[idx as usize];
+        return (((color>>18)&0x3f) as u8,((color>>10)&0x3f) as u8,((color>>2)&0x3f) as u8);
+    }
+
+    pub fn setDacEntry(&mut self,idx:u8,red:u8,green:u8,blue:u8)
+    {
+        self.vgaPalette[idx as usize]=(vga::expandDacComponent(red)<<16)|(vga::expandDacComponent(green)<<8)|vga::expandDacComponent(blue);
+    }
+
+    // attribute controller palette registers 00h-0fh, and 11h the overscan (border) color
+    pub fn setPaletteRegister(&mut self,idx:u8,val:u8)
+    {
+        if (idx<0x10) || (idx==0x11)
+        {
+            self.vgaRegister3c0Values[idx as usize]=val&0x3f;
+        }
+    }
+
+    pub fn getPaletteRegister(&self,idx:u8) -> u8
+    {
+        if (idx<0x10) || (idx==0x11)
+        {
+            return self.vgaRegister3c0Values[idx as usize];
+        }
+        return 0;
+    }
+
+    // INT 10h,10 AL=13h/1ah - color paging: attribute 10h bit 7 gives 16 pages of 16 colors instead of 4 of 64,
+    // the page is in the color select register (14h)
+    pub fn setColorPagingMode(&mut self,sixteenPages:bool)
+    {
+        if sixteenPages { self.vgaRegister3c0Values[0x10]|=0x80; }
+        else { self.vgaRegister3c0Values[0x10]&=!0x80; }
+    }
+
+    pub fn setColorPage(&mut self,page:u8)
+    {
+        if (self.vgaRegister3c0Values[0x10]&0x80)!=0 { self.vgaRegister3c0Values[0x14]=page&0x0f; }
+        else { self.vgaRegister3c0Values[0x14]=(page&0x03)<<2; }
+    }
+
+    // (16 pages mode, current page)
+    pub fn getColorPageState(&self) -> (bool,u8)
+    {
+        let colorSelect=self.vgaRegister3c0Values[0x14];
+        if (self.vgaRegister3c0Values[0x10]&0x80)!=0 { return (true,colorSelect&0x0f); }
+        return (false,(colorSelect>>2)&0x03);
+    }
+
+    // char and attribute of a cell of the displayed text page
+    pub fn getScreenCell(&self,col:usize,row:usize) -> (u8,u8)
+    {
+        let cols=self.getTextGeometry().0;
+        let bufMask=if self.mode==0x07 { 0x0fff } else { 0x7fff };
+        let cellOffset=self.getDisplayStartOffset()+(((row*cols)+col)*2);
+        return (self.cgaFramebuffer[cellOffset&bufMask],self.cgaFramebuffer[(cellOffset+1)&bufMask]);
+    }
+
+    // the text screen as it's displayed, a string per row with the chars converted from cp437
+    pub fn getScreenText(&self) -> Vec<String>
+    {
+        let mut screenRows:Vec<String>=Vec::new();
+        if !self.isTextMode() { return screenRows; }
+
+        let (cols,rows,_charHeight)=self.getTextGeometry();
+        for row in 0..rows
+        {
+            let mut rowText=String::new();
+            for col in 0..cols
+            {
+                rowText.push(cp437ToChar(self.getScreenCell(col,row).0));
+            }
+            screenRows.push(rowText);
+        }
+        return screenRows;
+    }
+
+    // the attribute bytes of the displayed text page, one row per line, as hex
+    pub fn getScreenAttributes(&self) -> Vec<String>
+    {
+        let mut attrRows:Vec<String>=Vec::new();
+        if !self.isTextMode() { return attrRows; }
+
+        let (cols,rows,_charHeight)=self.getTextGeometry();
+        for row in 0..rows
+        {
+            let mut rowText=String::new();
+            for col in 0..cols
+            {
+                rowText.push_str(&format!("{:02x}",self.getScreenCell(col,row).1));
+            }
+            attrRows.push(rowText);
+        }
+        return attrRows;
+    }
+
+    // (column,row) of the hardware cursor on the displayed page, None if it's hidden or off screen
+    pub fn getScreenCursor(&self) -> Option<(usize,usize)>
+    {
+        let cursorStart=self.egaRegister3b5Values[0x0a];
+        if ((cursorStart>>5)&0x03)==1 { return None; }
+        if (cursorStart&0x1f)>(self.egaRegister3b5Values[0x0b]&0x1f) { return None; }
+
+        let (cols,rows,_charHeight)=self.getTextGeometry();
+        let cursorAddr=((((self.egaRegister3b5Values[0x0e] as usize)<<8)|(self.egaRegister3b5Values[0x0f] as usize))*2)&0x7fff;
+        let cellIdx=(cursorAddr.wrapping_sub(self.getDisplayStartOffset())&0x7fff)/2;
+        if cellIdx>=(cols*rows) { return None; }
+
+        return Some((cellIdx%cols,cellIdx/cols));
+    }
+
+    // renders the current frame and writes it to a PNG, at the guest resolution
+    pub fn saveScreenshot(&mut self,fname:&str) -> bool
+    {
+        let mut frame=videoFrame::new();
+        self.fbTobuf32(&mut frame);
+        return frame.savePng(fname);
+    }
+
+    // writes the characters of a text mode screen to fname (UTF-8), followed by their attributes if withAttributes
+    pub fn saveTextScreen(&self,fname:&str,withAttributes:bool) -> bool
+    {
+        if !self.isTextMode()
+        {
+            eprintln!("Video mode {:02x} is not a text mode, no text dump saved",self.mode);
+            return false;
+        }
+
+        let mut dump=String::new();
+        for rowText in self.getScreenText()
+        {
+            dump.push_str(&rowText);
+            dump.push('\n');
+        }
+
+        if withAttributes
+        {
+            dump.push('\n');
+            for rowText in self.getScreenAttributes()
+            {
+                dump.push_str(&rowText);
+                dump.push('\n');
+            }
+        }
+
+        if let Err(e)=fs::write(fname,dump)
+        {
+            eprintln!("Unable to save {} error:{}",fname,e);
+            return false;
+        }
+        return true;
+    }
+
+    pub fn getNumberOfColumns(&self) -> u16
+    {
+        if self.isTextMode()
+        {
+            return self.getTextGeometry().0 as u16;
+        }
+        else if (self.mode==0x04) || (self.mode==0x05) || (self.mode==0x0d) || (self.mode==0x13)
+        {
+            return 40;
+        }
+        else if self.mode==0x103
+        {
+            return 100;
+        }
+
+        return 80;
+    }
+
+    pub fn getNumberOfRows(&self) -> u16
+    {
+        if self.isTextMode()
+        {
+            return self.getTextGeometry().1 as u16;
+        }
+        return self.graphicsTextRows;
+    }
+
+    // EGA/VGA graphics modes, rendered from the planes through the VGA registers
+    pub fn isVgaGraphicsMode(&self) -> bool
+    {
+        return (self.mode>=0x0d) && (self.mode<=0x13);
+    }
+
+    // text pages fill the 32k at b800:0000, 8 pages of 80x25 or 16 pages of 40x25
+    pub fn getNumberOfPages(&self) -> usize
+    {
+        if (!self.isTextMode()) || (self.mode==0x07) { return 1; }
+        let maxPages=if self.getNumberOfColumns()==40 { 16 } else { 8 };
+        return std::cmp::max(1,std::cmp::min(maxPages,0x8000/self.getPageSize()));
+    }
+
+    // a page is the screen size rounded up to 256 bytes, 80x25 is 1000h, 80x50 is 2000h
+    pub fn getPageSize(&self) -> usize
+    {
+        if !self.isTextMode()
+        {
+            if self.getNumberOfColumns()==40 { return 0x800; }
+            return 0x1000;
+        }
+
+        let (cols,rows,_charHeight)=self.getTextGeometry();
+        return (((cols*rows*2)+0xff)&!0xff).max(0x800);
+    }
+
+    pub fn getPageOffset(&self,page:usize) -> usize
+    {
+        return (page%self.getNumberOfPages())*self.getPageSize();
+    }
+
+    // CRTC start address (registers 0ch/0dh) is in character cells
+    pub fn getDisplayStartOffset(&self) -> usize
+    {
+        let startAddr=((self.egaRegister3b5Values[0x0c] as usize)<<8)|(self.egaRegister3b5Values[0x0d] as usize);
+        return startAddr*2;
+    }
+
+    // INT 10h,5
+    pub fn selectActivePage(&mut self,page:usize)
+    {
+        if page>=self.getNumberOfPages() { return; }
+
+        self.activePage=page;
+        let startAddr=self.getPageOffset(page)/2;
+        self.egaRegister3b5Values[0x0c]=(startAddr>>8) as u8;
+        self.egaRegister3b5Values[0x0d]=(startAddr&0xff) as u8;
+        self.updateCrtcCursor();
+    }
+
+    fn textCellOffset(&self,page:usize,x:usize,y:usize) -> usize
+    {
+        let numColumns=self.getNumberOfColumns() as usize;
+        return (self.getPageOffset(page)+(((y*numColumns)+x)*2))&0x7fff;
+    }
+
+    pub fn getCursorPosition(&self,page:usize) -> (usize,usize)
+    {
+        return self.pageCursors[page%self.pageCursors.len()];
+    }
+
+    pub fn setCursorPosition(&mut self,px:u16,py:u16,page:usize)
+    {
+        let numPages=self.pageCursors.len();
+        self.pageCursors[page%numPages]=(px as usize,py as usize);
+        self.updateCrtcCursor();
+    }
+
+    // moves the hardware cursor (CRTC 0eh/0fh) where the active page cursor is
+    fn updateCrtcCursor(&mut self)
+    {
+        if !self.isTextMode() { return; }
+
+        let (cx,cy)=self.pageCursors[self.activePage];
+        let cursorAddr=(self.getPageOffset(self.activePage)/2)+(cy*(self.getNumberOfColumns() as usize))+cx;
+        self.egaRegister3b5Values[0x0e]=((cursorAddr>>8)&0xff) as u8;
+        self.egaRegister3b5Values[0x0f]=(cursorAddr&0xff) as u8;
+    }
+
+    // INT 10h,1 - CH = start scanline and blink bits, CL = end scanline
+    pub fn setCursorShape(&mut self,startLine:u8,endLine:u8)
+    {
+        let mut cstart=startLine&0x1f;
+        let mut cend=endLine&0x1f;
+        let charHeight=((self.egaRegister3b5Values[0x09]&0x1f) as u16)+1;
+
+        // like the VGA BIOS, a CGA 8 scanlines cursor gets stretched to the char height
+        if (charHeight>8) && (cstart<8) && (cend<8)
+        {
+            cstart=(((cstart as u16)*charHeight)/8) as u8;
+            cend=((((cend as u16)+1)*charHeight)/8-1) as u8;
+        }
+
+        self.egaRegister3b5Values[0x0a]=(startLine&0x60)|cstart;
+        self.egaRegister3b5Values[0x0b]=cend;
+    }
+
+    pub fn getCursorShape(&self) -> u16
+    {
+        return ((self.egaRegister3b5Values[0x0a] as u16)<<8)|(self.egaRegister3b5Values[0x0b] as u16);
+    }
+
+    // true in the "on" half of a blink cycle lasting framesPerCycle emulated frames
+    fn getBlinkPhase(&self,framesPerCycle:u64) -> bool
+    {
+        return (self.frameCounter%framesPerCycle)<(framesPerCycle/2);
+    }
+
+    pub fn putpixel(&mut self,color:u8,column:u16,row:u16)
+    {
+        if self.mode==0x04 || self.mode==0x05
+        {
+            self.putCGA320x200pixel(color,column as u32,row as u32);
+        }
+        else if self.isVgaGraphicsMode() && (self.mode!=0x13)
+        {
+            self.putPlanarPixel(color,column as u32,row as u32);
+        }
+        else
+        {
+            emuLog::fatalError(&format!("Bailing out: vga::putpixel for unhandled mode {:02x}",self.mode),emuLog::EXIT_ABORTED);
+        }
+    }
+
+    //
+
+    pub fn readMemory(&mut self,addr:i64) -> u8
+    {
+        if let Some(idx)=self.getVesaWindowIndex(addr)
+        {
+            return self.framebuffer[idx];
+        }
+        else if let Some(offs)=self.getPlanarOffset(addr)
+        {
+            return self.readPlanar(offs);
+        }
+        else if let Some(idx)=self.getTextBufferIndex(addr)
+        {
+            return self.cgaFramebuffer[idx];
+        }
+
+        return 0xff;
+    }
+
+    // offset inside the a000:0000 window, if the graphics controller maps it (GC 06h bits 2-3)
+    fn getPlanarOffset(&self,addr:i64) -> Option<usize>
+    {
+        let memoryMap=(self.egaRegister3cfValues[6]>>2)&0x03;
+        if (addr>=0xa0000) && (addr<=0xaffff) && (memoryMap<2)
+        {
+            return Some((addr-0xa0000) as usize);
+        }
+
+        return None;
+    }
+
+    // (offset inside the planes, planes written, plane read) by a cpu access, depending on sequencer memory mode
+    fn getPlaneAccess(&self,offs:usize) -> (usize,u8,usize)
+    {
+        let memoryMode=self.egaRegister3c5Values[4];
+        let readMapSelect=(self.egaRegister3cfValues[4]&0x03) as usize;
+
+        if (memoryMode&0x08)!=0
+        {
+            // chain-4: the low 2 address bits select the plane
+            return (offs>>2,1<<(offs&0x03),offs&0x03);
+        }
+        else if (memoryMode&0x04)==0
+        {
+            // odd/even: even addresses go to planes 0/2, odd ones to planes 1/3
+            let oddPlane=offs&0x01;
+            return (offs&!1,if oddPlane!=0 { 0x0a } else { 0x05 },(readMapSelect&0x02)|oddPlane);
+        }
+
+        return (offs,0x0f,readMapSelect);
+    }
+
+    fn readPlanar(&mut self,offs:usize) -> u8
+    {
+        let (planeOffs,_planes,readPlane)=self.getPlaneAccess(offs);
+        for plane in 0..4
+        {
+            self.egaDataLatch[plane]=self.framebuffer[planeIndex(planeOffs,plane)];
+        }
+
+        // read mode 1: bits set where the pixel color matches GC 02h, planes not in GC 07h don't care
+        if (self.egaRegister3cfValues[5]&0x08)!=0
+        {
+            let colorCompare=self.egaRegister3cfValues[2];
+            let colorDontCare=self.egaRegister3cfValues[7];
+            let mut mismatch:u8=0;
+            for plane in 0..4
+            {
+                if (colorDontCare&(1<<plane))!=0
+                {
+                    let planeColor=if (colorCompare&(1<<plane))!=0 { 0xff } else { 0 };
+                    mismatch|=self.egaDataLatch[plane]^planeColor;
+                }
+            }
+            return !mismatch;
+        }
+
+        return self.egaDataLatch[readPlane];
+    }
+
+    // maps b000:0000-b000:ffff to the text/CGA buffer, depending on the card
+    fn getTextBufferIndex(&self,addr:i64) -> Option<usize>
+    {
+        if self.cardType==machineType::machineMDA
+        {
+            // 4k, mirrored up to b7fff
+            if (addr>=0xb0000) && (addr<=0xb7fff) { return Some(((addr-0xb0000)&0x0fff) as usize); }
+        }
+        else if self.cardType==machineType::machineHercules
+        {
+            // 2 graphics pages of 32k, the 2nd one only if enabled by 3bfh
+            if (addr>=0xb0000) && (addr<=0xb7fff) { return Some((addr-0xb0000) as usize); }
+            if (addr>=0xb8000) && (addr<=0xbffff) && ((self.herculesConfigReg&0x02)!=0) { return Some((addr-0xb0000) as usize); }
+        }
+        else if self.cardType==machineType::machineCGA
+        {
+            if (addr>=0xb8000) && (addr<=0xbffff) { return Some((addr-0xb8000) as usize); }
+        }
         else
         {
-            println!("Bailing out: vga::cannot switch to mode {:02x}",videomodeNum);
-            process::exit(0x0100);
+            // EGA/VGA: GC 06h bits 2-3, 2 is b000:0000 (mode 7), 3 is b800:0000
+            let memoryMap=(self.egaRegister3cfValues[6]>>2)&0x03;
+            if (memoryMap==2) && (addr>=0xb0000) && (addr<=0xb7fff) { return Some((addr-0xb0000) as usize); }
+            if (memoryMap==3) && (addr>=0xb8000) && (addr<=0xbffff) { return Some((addr-0xb8000) as usize); }
+        }
+
+        return None;
+    }
+
+    pub fn readMemory16(&mut self,addr:i64) -> u16
+    {
+        let lobyte=self.readMemory(addr) as u16;
+        let hibyte=self.readMemory(addr+1) as u16;
+        return lobyte|(hibyte<<8);
+    }
+
+    pub fn writeMemory(&mut self,addr:i64,val:u8)
+    {
+        if let Some(idx)=self.getVesaWindowIndex(addr)
+        {
+            self.framebuffer[idx]=val;
+        }
+        else if let Some(offs)=self.getPlanarOffset(addr)
+        {
+            self.writePlanar(offs,val);
+        }
+        else if let Some(idx)=self.getTextBufferIndex(addr)
+        {
+            self.cgaFramebuffer[idx]=val;
+        }
+    }
+
+    // graphics controller logic unit (GC 03h bits 3-4) between cpu data and latch
+    fn applyLogicOp(&self,data:u8,latch:u8) -> u8
+    {
+        let logicOp=(self.egaRegister3cfValues[3]>>3)&0x03;
+        if logicOp==1 { return data&latch; }
+        else if logicOp==2 { return data|latch; }
+        else if logicOp==3 { return data^latch; }
+        return data;
+    }
+
+    fn writePlanar(&mut self,offs:usize,val:u8)
+    {
+        let (planeOffs,planes,_readPlane)=self.getPlaneAccess(offs);
+        let mapMask=self.egaRegister3c5Values[2]&planes;
+        let writeMode=self.egaRegister3cfValues[5]&0x03;
+        let setReset=self.egaRegister3cfValues[0];
+        let enableSetReset=self.egaRegister3cfValues[1];
+        let rotateCount=(self.egaRegister3cfValues[3]&0x07) as u32;
+        let mut bitMask=self.egaRegister3cfValues[8];
+
+        let rotatedVal=val.rotate_right(rotateCount);
+        if writeMode==3
+        {
+            // write mode 3: rotated cpu data ANDed with the bit mask is the mask, set/reset the color
+            bitMask&=rotatedVal;
+        }
+
+        for plane in 0..4
+        {
+            if (mapMask&(1<<plane))==0 { continue; }
+
+            let latch=self.egaDataLatch[plane];
+            let setResetVal=if (setReset&(1<<plane))!=0 { 0xff } else { 0 };
+            let realVal;
+
+            if writeMode==1
+            {
+                // write mode 1: latches are copied as they are
+                realVal=latch;
+            }
+            else
+            {
+                let data;
+                if writeMode==0
+                {
+                    data=if (enableSetReset&(1<<plane))!=0 { setResetVal } else { rotatedVal };
+                }
+                else if writeMode==2
+                {
+                    // write mode 2: cpu data bits 0-3 are the color
+                    data=if (val&(1<<plane))!=0 { 0xff } else { 0 };
+                }
+                else
+                {
+                    data=setResetVal;
+                }
+
+                let aluVal=self.applyLogicOp(data,latch);
+                realVal=(aluVal&bitMask)|(latch&(!bitMask));
+            }
+
+            self.framebuffer[planeIndex(planeOffs,plane)]=realVal;
         }
     }
 
-    pub fn getNumberOfColumns(&self) -> u16
+    pub fn writeMemory16(&mut self,addr:i64,val:u16)
     {
-        if (self.mode==0x00) || (self.mode==0x01)
-        {
-            return 40;
-        }
-
-        return 80;
+        self.writeMemory(addr,(val&0xff) as u8);
+        self.writeMemory(addr+1,((val>>8)&0xff) as u8);
     }
 
-    pub fn getCursorPosition(&self) -> (usize,usize)
+    fn handleScrollMode2(&mut self)
     {
-        return (self.cursorx,self.cursory);
+        let page=self.activePage;
+        let (cx,cy)=self.pageCursors[page];
+        let rows=self.getNumberOfRows() as usize;
+        if cy==rows
+        {
+            let columns=self.getNumberOfColumns();
+            self.scrollWindow(true,1,0,0,(rows-1) as u16,columns-1,0);
+            self.pageCursors[page]=(cx,cy-1);
+        }
+        self.updateCrtcCursor();
     }
 
-    pub fn setCursorPosition(&mut self,px:u16,py:u16)
+    // (char height, bytes per char column, bytes per scanline, planar memory)
+    fn getGraphicsCharGeometry(&self) -> (usize,usize,usize,bool)
     {
-        self.cursorx=px as usize;
-        self.cursory=py as usize;
+        if (self.mode==0x04) || (self.mode==0x05) { return (8,2,80,false); }
+        else if self.mode==0x06 { return (8,1,80,false); }
+        else if self.mode==0x0d { return (self.graphicsCharHeight,1,40,true); }
+        else if (self.mode>=0x0e) && (self.mode<=0x12) { return (self.graphicsCharHeight,1,80,true); }
+        return (8,8,320,false); // 0x13
     }
 
-    pub fn putpixel(&mut self,color:u8,column:u16,row:u16)
+    fn getScanlineOffset(&self,y:usize,bytesPerLine:usize) -> usize
     {
-        if self.mode==0x04 || self.mode==0x05
-        {
-            self.putCGA320x200pixel(color,column as u32,row as u32);
-        }
-        else
+        if (self.mode==0x04) || (self.mode==0x05) || (self.mode==0x06)
         {
-            println!("Bailing out: vga::putpixel for unhandled mode {:02x}",self.mode);
-            process::exit(0x0100);
+            // CGA interlaced banks
+            return ((y&1)*0x2000)+((y>>1)*bytesPerLine);
         }
+        return y*bytesPerLine;
     }
 
-    //
+    pub fn isTextMode(&self) -> bool
+    {
+        return (self.mode<=3) || (self.mode==0x07);
+    }
 
-    pub fn readMemory(&mut self,addr:i64) -> u8
+    fn copyCharRow(&mut self,srcRow:usize,dstRow:usize,left:usize,right:usize)
     {
-        if (addr>=0xa0000) && (addr<=(0xaffff))
+        if self.isTextMode()
         {
-            let egaOffs=addr&0x1fff;
-            for plane in 0..4
+            for col in left..=right
             {
-                self.egaDataLatch[plane]=self.framebuffer[(egaOffs+((plane as i64)*0x2000)) as usize];
+                let src=self.textCellOffset(self.activePage,col,srcRow);
+                let dst=self.textCellOffset(self.activePage,col,dstRow);
+                self.cgaFramebuffer[dst]=self.cgaFramebuffer[src];
+                self.cgaFramebuffer[dst+1]=self.cgaFramebuffer[src+1];
             }
-            return self.framebuffer[(addr-0xa0000) as usize];
+            return;
         }
-        else if (addr>=0xb8000) && (addr<=0xbffff)
+
+        let (charHeight,bytesPerChar,bytesPerLine,planar)=self.getGraphicsCharGeometry();
+        for scanline in 0..charHeight
         {
-            return self.cgaFramebuffer[(addr-0xb8000) as usize];
+            let srcOffs=self.getScanlineOffset((srcRow*charHeight)+scanline,bytesPerLine)+(left*bytesPerChar);
+            let dstOffs=self.getScanlineOffset((dstRow*charHeight)+scanline,bytesPerLine)+(left*bytesPerChar);
+            let len=(right-left+1)*bytesPerChar;
+            if self.mode<=6
+            {
+                self.cgaFramebuffer.copy_within(srcOffs..srcOffs+len,dstOffs);
+            }
+            else if planar
+            {
+                // the 4 planes of a byte are next to each other
+                self.framebuffer.copy_within(planeIndex(srcOffs,0)..planeIndex(srcOffs+len,0),planeIndex(dstOffs,0));
+            }
+            else
+            {
+                self.framebuffer.copy_within(srcOffs..srcOffs+len,dstOffs);
+            }
         }
-
-        return 0;
-    }
-
-    pub fn readMemory16(&mut self,addr:i64) -> u16
-    {
-        let lobyte=self.readMemory(addr) as u16;
-        let hibyte=self.readMemory(addr+1) as u16;
-        return lobyte|(hibyte<<8);
     }
 
-    pub fn writeMemory(&mut self,addr:i64,val:u8)
+    fn fillCharRow(&mut self,row:usize,left:usize,right:usize,fillAttr:u8)
     {
-        if (addr>=0xa0000) && (addr<=0xaffff)
+        if self.isTextMode()
         {
-            let mut planeSize=0x2000;
-            if self.mode==0x10
+            for col in left..=right
             {
-                planeSize=0x8000;
+                let dst=self.textCellOffset(self.activePage,col,row);
+                self.cgaFramebuffer[dst]=0x20;
+                self.cgaFramebuffer[dst+1]=fillAttr;
             }
+            return;
+        }
 
-            // if ega videomode is 0x0d|0x10 and write mode is 0
-            if ((self.mode==0x0d)||(self.mode==0x10)) && (self.egaRegister3cfValues[5]==0)
+        let (charHeight,bytesPerChar,bytesPerLine,planar)=self.getGraphicsCharGeometry();
+        for scanline in 0..charHeight
+        {
+            let dstOffs=self.getScanlineOffset((row*charHeight)+scanline,bytesPerLine)+(left*bytesPerChar);
+            let len=(right-left+1)*bytesPerChar;
+            if (self.mode==0x04) || (self.mode==0x05)
             {
-                let mut realVal=val;
-                let bitplane=self.egaRegister3c5Values[2];
-                let reg3_ega3cf=self.egaRegister3cfValues[3];
-                let reg8_ega3cf=self.egaRegister3cfValues[8];
-
-                let latchNum=((addr-0xa0000)>>13)&0x03;
-
-                // data rotate reg bits 3&4: 
-                if (reg3_ega3cf&0x18)==0x18
-                {
-                    // 1,1 -> data is XORed with latch data
-                    realVal=realVal^self.egaDataLatch[latchNum as usize];
-                }
-                else if (reg3_ega3cf&0x18)==0x8
-                {
-                    // 0,1 -> data is AND'd with latch data
-                    realVal=realVal&self.egaDataLatch[latchNum as usize];
-                }
-                else if (reg3_ega3cf&0x18)==0x10
-                {
-                    // 1,0 -> data is OR'd with latch data
-                    realVal=realVal|self.egaDataLatch[latchNum as usize];
-                }
-                else if reg3_ega3cf!=0
-                {
-                    println!("Bailing out: vga::other function {:02x}",reg3_ega3cf);
-                    process::exit(0x0100);
-                }
-
-                realVal = (realVal & reg8_ega3cf) | (self.egaDataLatch[latchNum as usize] & (!reg8_ega3cf));
-
-                if (bitplane&0x01)>0
-                {
-                    self.framebuffer[((addr-0xa0000)&0xffff) as usize]=realVal;
-                }
-                if (bitplane&0x02)>0
-                {
-                    self.framebuffer[((addr-0xa0000+planeSize)&0xffff) as usize]=realVal;
-                }
-                if (bitplane&0x04)>0
-                {
-                    self.framebuffer[((addr-0xa0000+(planeSize*2))&0xffff) as usize]=realVal;
-                }
-                if (bitplane&0x08)>0
-                {
-                    self.framebuffer[((addr-0xa0000+(planeSize*3))&0xffff) as usize]=realVal;
-                }
+                self.cgaFramebuffer[dstOffs..dstOffs+len].fill((fillAttr&0x03)*0x55);
             }
-            else if ((self.mode==0x0d)||(self.mode==0x10)) && (self.egaRegister3cfValues[5]==2)
+            else if self.mode==0x06
             {
-                let bitplane=0xff;//self.egaRegister3c5Values[2];
-                let reg8_ega3cf=self.egaRegister3cfValues[8];
-
-                let vala = if (val & 1)>0 { 0xff } else { 0 };
-                let valb = if (val & 2)>0 { 0xff } else { 0 };
-                let valc = if (val & 4)>0 { 0xff } else { 0 };
-                let vald = if (val & 8)>0 { 0xff } else { 0 };
-
-                if (bitplane&0x01)>0
-                {
-                    self.framebuffer[((addr-0xa0000)&0xffff) as usize]=(vala & reg8_ega3cf) | (self.egaDataLatch[0] & (!reg8_ega3cf));
-                }
-                if (bitplane&0x02)>0
-                {
-                    self.framebuffer[((addr-0xa0000+planeSize)&0xffff) as usize]=(valb & reg8_ega3cf) | (self.egaDataLatch[1] & (!reg8_ega3cf));
-                }
-                if (bitplane&0x04)>0
-                {
-                    self.framebuffer[((addr-0xa0000+(planeSize*2))&0xffff) as usize]=(valc & reg8_ega3cf) | (self.egaDataLatch[2] & (!reg8_ega3cf));
-                }
-                if (bitplane&0x08)>0
+                self.cgaFramebuffer[dstOffs..dstOffs+len].fill(if (fillAttr&0x01)>0 { 0xff } else { 0 });
+            }
+            else if planar
+            {
+                for offs in dstOffs..dstOffs+len
                 {
-                    self.framebuffer[((addr-0xa0000+(planeSize*3))&0xffff) as usize]=(vald & reg8_ega3cf) | (self.egaDataLatch[3] & (!reg8_ega3cf));
+                    for plane in 0..4
+                    {
+                        self.framebuffer[planeIndex(offs,plane)]=if ((fillAttr>>plane)&0x01)>0 { 0xff } else { 0 };
+                    }
                 }
             }
             else
             {
-                self.framebuffer[(addr-0xa0000) as usize]=val;
+                self.framebuffer[dstOffs..dstOffs+len].fill(fillAttr);
             }
         }
-        else if (addr>=0xb8000) && (addr<=0xbffff)
-        {
-            self.cgaFramebuffer[(addr-0xb8000) as usize]=val;
-        }
-    }
-
-    pub fn writeMemory16(&mut self,addr:i64,val:u16)
-    {
-        self.writeMemory(addr,(val&0xff) as u8);
-        self.writeMemory(addr+1,((val>>8)&0xff) as u8);
     }
 
-    pub fn clrScreenMode2(&mut self)
+    // INT 10h,6 and INT 10h,7 - scrolls the top,left-bottom,right window by numLines character rows
+    // numLines=0 blanks the whole window, fillAttr is the attribute (text) or color (graphics) of the new lines
+    pub fn scrollWindow(&mut self,scrollUp:bool,numLines:u16,top:u16,left:u16,bottom:u16,right:u16,fillAttr:u8)
     {
-        let mut pos=0;
-        for _c in 0..80*2*25
+        if (!self.isTextMode()) && (self.mode!=0x04) && (self.mode!=0x05) && (self.mode!=0x06) && 
+           (!self.isVgaGraphicsMode())
         {
-            self.cgaFramebuffer[pos]=0;
-            pos+=1;
+            return;
         }
-    }
 
-    fn handleScrollMode2(&mut self)
-    {
-        let mut pos=0;
-        let columns=80;
-        if self.cursory==25
+        let cols=self.getNumberOfColumns() as usize;
+        let rows=self.getNumberOfRows() as usize;
+
+        let top=top as usize;
+        let left=left as usize;
+        let bottom=std::cmp::min(bottom as usize,rows-1);
+        let right=std::cmp::min(right as usize,cols-1);
+        if (top>bottom) || (left>right) { return; }
+
+        let height=bottom-top+1;
+        let mut lines=numLines as usize;
+        if (lines==0) || (lines>height) { lines=height; }
+
+        for i in 0..height
         {
-            for _row in 0..24
+            let dstRow=if scrollUp { top+i } else { bottom-i };
+            if (i+lines)<height
             {
-                for _c in 0..columns*2
-                {
-                    self.cgaFramebuffer[pos]=self.cgaFramebuffer[pos+(columns*2)];
-                    pos+=1;
-                }
-            }            
-
-            for _c in 0..columns*2
+                let srcRow=if scrollUp { dstRow+lines } else { dstRow-lines };
+                self.copyCharRow(srcRow,dstRow,left,right);
+            }
+            else
             {
-                self.cgaFramebuffer[pos]=0;
-                pos+=1;
+                self.fillCharRow(dstRow,left,right,fillAttr);
             }
-
-            self.cursory-=1;
         }
     }
 
-    pub fn readCharAttributeAtCursorPos(&self) -> u16
+    pub fn readCharAttributeAtCursorPos(&self,page:usize) -> u16
     {
-        let numColumns=80;
-        let ch:u16=self.cgaFramebuffer[((self.cursorx)*2)+(self.cursory*numColumns*2)] as u16;
-        let attr:u16=self.cgaFramebuffer[((self.cursorx)*2)+(self.cursory*numColumns*2)+1] as u16;
+        let (cx,cy)=self.getCursorPosition(page);
+        let cellOffs=self.textCellOffset(page,cx,cy);
+        let ch:u16=self.cgaFramebuffer[cellOffs] as u16;
+        let attr:u16=self.cgaFramebuffer[cellOffs+1] as u16;
         return ch|(attr<<8);
     }
 
     pub fn writeCharsWithAttribute(&mut self,ochar:u16,bgcol:u16,attrib:u16,nchars:u16,seg:u16,offs:u16,pmachine:&machine)
     {
         // if in textmode
-        if (self.mode==0) || (self.mode==1)
-        {
-            let numColumns=40;
-            for _i in 0..nchars
-            {
-                self.cgaFramebuffer[((self.cursorx+_i as usize)*2)+(self.cursory*numColumns*2)]=ochar as u8;
-                self.cgaFramebuffer[((self.cursorx+_i as usize)*2)+(self.cursory*numColumns*2)+1]=attrib as u8;
-            }
-        }
-        else if (self.mode==2) || (self.mode==3)
+        if self.isTextMode()
         {
-            let numColumns=80;
+            // BH is the display page
+            let page=bgcol as usize;
+            let (cx,cy)=self.getCursorPosition(page);
             for _i in 0..nchars
             {
-                self.cgaFramebuffer[((self.cursorx+_i as usize)*2)+(self.cursory*numColumns*2)]=ochar as u8;
-                self.cgaFramebuffer[((self.cursorx+_i as usize)*2)+(self.cursory*numColumns*2)+1]=attrib as u8;
+                let cellOffs=self.textCellOffset(page,cx+(_i as usize),cy);
+                self.cgaFramebuffer[cellOffs]=ochar as u8;
+                self.cgaFramebuffer[cellOffs+1]=attrib as u8;
             }
         }
         else if (self.mode==4) || (self.mode==5) || (self.mode==0x0d)
@@ -363,15 +1391,15 @@ impl vga
                 }
             }
 
+            let (cx,cy)=self.getCursorPosition(0);
             for _i in 0..nchars
             {
                 self.drawCharOnScreen(
                     8,8,
                     32,
                     ochar as u32,
-                    self.cursory as u32,
-                    ((self.cursorx+(_i as usize))%40) as u32,
-                    320,
+                    cy as u32,
+                    ((cx+(_i as usize))%40) as u32,
                     attrib as u32,bgcol as u32,
                     tempChar.clone());
             }
@@ -381,22 +1409,16 @@ impl vga
     pub fn outputCharToStdout(&mut self,ochar:u8)
     {
         // if in textmode
-        if (self.mode==0) || (self.mode==1) || (self.mode==2) || (self.mode==3)
+        if self.isTextMode()
         {
             let charCol=7;
-            let mut numColumns=80;
-            if (self.mode==0) || (self.mode==1) 
-            {
-                numColumns=40;
-            }
+            let numColumns=self.getNumberOfColumns() as usize;
+            let page=self.activePage;
+            let (mut cx,mut cy)=self.pageCursors[page];
 
             if ochar==13
             {
-                self.cgaFramebuffer[(self.cursorx*2)+(self.cursory*numColumns*2)]=0;
-                self.cgaFramebuffer[(self.cursorx*2)+(self.cursory*numColumns*2)+1]=0x0;
-
-                self.cursorx=0;
-                self.cursory+=1;
+                self.pageCursors[page]=(0,cy+1);
                 self.handleScrollMode2();
             }
             else if ochar==10
@@ -404,26 +1426,23 @@ impl vga
             }
             else if ochar==8
             {
-                self.cgaFramebuffer[(self.cursorx*2)+(self.cursory*numColumns*2)]=0;
-                self.cgaFramebuffer[(self.cursorx*2)+(self.cursory*numColumns*2)+1]=0x0;
-                self.cursorx-=1;
-                self.cgaFramebuffer[(self.cursorx*2)+(self.cursory*numColumns*2)]=22;
-                self.cgaFramebuffer[(self.cursorx*2)+(self.cursory*numColumns*2)+1]=charCol;
+                if cx>0 { cx-=1; }
+                self.pageCursors[page]=(cx,cy);
+                self.updateCrtcCursor();
             }
             else
             {
-                self.cgaFramebuffer[(self.cursorx*2)+(self.cursory*numColumns*2)]=ochar;
-                self.cgaFramebuffer[(self.cursorx*2)+(self.cursory*numColumns*2)+1]=charCol;
-                self.cursorx+=1;
-                if self.cursorx==numColumns
+                let cellOffs=self.textCellOffset(page,cx,cy);
+                self.cgaFramebuffer[cellOffs]=ochar;
+                self.cgaFramebuffer[cellOffs+1]=charCol;
+                cx+=1;
+                if cx==numColumns
                 {
-                    self.cursorx=0;
-                    self.cursory+=1;
-                    self.handleScrollMode2();
+                    cx=0;
+                    cy+=1;
                 }
-
-                self.cgaFramebuffer[(self.cursorx*2)+(self.cursory*numColumns*2)]=22;
-                self.cgaFramebuffer[(self.cursorx*2)+(self.cursory*numColumns*2)+1]=charCol;
+                self.pageCursors[page]=(cx,cy);
+                self.handleScrollMode2();
             }
         }
     }
@@ -445,26 +1464,65 @@ impl vga
         self.cgaFramebuffer[(adder+curbyte+(curline*80)) as usize]|=(curcol&0x03)<<(cur2bits*2);
     }
 
-    fn putEGA320x200pixel(&mut self,curcol:u8,pixelx:u32,pixely:u32)
+    // 16 color planar modes, a scanline is as long as the CRTC offset register says
+    fn putPlanarPixel(&mut self,curcol:u8,pixelx:u32,pixely:u32)
     {
-        if pixelx>=320 { return };
-        if pixely>=200 { return };
+        let bytesPerLine=(self.egaRegister3b5Values[0x13] as u32)*2;
+        if pixelx>=(bytesPerLine*8) { return };
+        if pixely>=480 { return };
 
-        let curbyte=(pixelx/8)%40;
+        let curbyte=(pixelx/8)+(pixely*bytesPerLine);
         let curbit=7-(pixelx%8);
-        let curline=pixely;
 
-        self.framebuffer[(curbyte+(curline*40)) as usize]&=!(1<<curbit);
-        self.framebuffer[(curbyte+(curline*40)) as usize]|=(curcol&0x1)<<curbit;
+        for plane in 0..4
+        {
+            let idx=planeIndex(curbyte as usize,plane);
+            self.framebuffer[idx]&=!(1<<curbit);
+            self.framebuffer[idx]|=((curcol>>plane)&0x1)<<curbit;
+        }
+    }
 
-        self.framebuffer[(0x2000+curbyte+(curline*40)) as usize]&=!(1<<curbit);
-        self.framebuffer[(0x2000+curbyte+(curline*40)) as usize]|=((curcol&0x2)>>1)<<curbit;
+    // a scanline of a text char as 9 dots, bit 8 is the leftmost one
+    // the VGA reads it from the character generator RAM in plane 2, the 6845 cards from their ROM
+    // line drawing chars c0h-dfh repeat the 8th dot as 9th (on the VGA if attribute 10h bit 2 is set)
+    fn getTextGlyphLine(&self,charNum:u8,line:usize,fontBase:usize) -> u16
+    {
+        let glyphLine:u8;
+        let lineGraphics:bool;
+        if self.hasMc6845()
+        {
+            let fontHeight=if self.isMonoAdapter() { 14 } else { 16 };
+            let romFont=if self.isMonoAdapter() { &self.romFont8x14 } else { &self.romFont8x16 };
+            glyphLine=if line<fontHeight { romFont[((charNum as usize)*fontHeight)+line] } else { 0 };
+            lineGraphics=true;
+        }
+        else
+        {
+            glyphLine=if line<32 { self.framebuffer[planeIndex(fontBase+((charNum as usize)*32)+line,2)] } else { 0 };
+            lineGraphics=(self.vgaRegister3c0Values[0x10]&0x04)!=0;
+        }
 
-        self.framebuffer[(0x4000+curbyte+(curline*40)) as usize]&=!(1<<curbit);
-        self.framebuffer[(0x4000+curbyte+(curline*40)) as usize]|=((curcol&0x4)>>2)<<curbit;
+        let mut dots=(glyphLine as u16)<<1;
+        if lineGraphics && (charNum>=0xc0) && (charNum<=0xdf) && ((glyphLine&0x01)!=0)
+        {
+            dots|=0x01;
+        }
+        return dots;
+    }
 
-        self.framebuffer[(0x6000+curbyte+(curline*40)) as usize]&=!(1<<curbit);
-        self.framebuffer[(0x6000+curbyte+(curline*40)) as usize]|=((curcol&0x8)>>3)<<curbit;
+    // a text mode char cell, cellHeight output lines high
+    fn drawTextChar(&mut self,charNum:u8,fontBase:usize,row:usize,col:usize,cellHeight:usize,resx:usize,fgCol:u32,bgCol:u32)
+    {
+        let mut destPos=(col*9)+(row*cellHeight*resx);
+        for y in 0..cellHeight
+        {
+            let dots=self.getTextGlyphLine(charNum,y,fontBase);
+            for x in 0..9
+            {
+                self.tempFramebuf[destPos+x]=if (dots&(0x100>>x))!=0 { fgCol } else { bgCol };
+            }
+            destPos+=resx;
+        }
     }
 
     fn drawCharOnScreen(&mut self,
@@ -473,7 +1531,6 @@ impl vga
         numCharsPerRow:u32,
         charNum:u32,
         row:u32,col:u32,
-        scrInc:u32,
         fgCol:u32,bgCol:u32,
         charVec:Vec<u8>)
     {
@@ -483,25 +1540,7 @@ impl vga
         let mut dstx:u32=col*charDimX;
         let mut dsty:u32=row*charDimY;
 
-        if (self.mode==0x02) || (self.mode==0x03)
-        {
-            let colorArr=Vec::from([bgCol,fgCol]);
-            let mut destPos=dstx+(dsty*scrInc);
-            for _y in 0..charDimY
-            {
-                for _x in 0..charDimX
-                {
-                    let curVal=self.font9x16data[srcy as usize][srcx as usize];
-                    self.tempFramebuf[destPos as usize]=colorArr[curVal as usize];
-                    destPos+=1;
-                    srcx+=1;
-                }
-                destPos+=scrInc-charDimX;
-                srcy+=1;
-                srcx=(charNum%numCharsPerRow)*charDimX;
-            }
-        }
-        else if (self.mode==0x04) || (self.mode==0x05)
+        if (self.mode==0x04) || (self.mode==0x05)
         {
             let mut pos=0;
             for _y in 0..charDimY
@@ -551,7 +1590,7 @@ impl vga
                         curVal=charVec[pos] as u32;
                     }
                     let col=if curVal>0 { fgCol } else { bgCol };
-                    self.putEGA320x200pixel(col as u8,dstx,dsty);
+                    self.putPlanarPixel(col as u8,dstx,dsty);
                     dstx+=1;
                     srcx+=1;
                     pos+=1;
@@ -565,34 +1604,72 @@ impl vga
         }
     }
 
+    // VGA pixel mask, anded with the color index before the DAC lookup
+    pub fn write0x3c6(&mut self,val: u8)
+    {
+        self.vgaPixelMask=val;
+    }
+
+    pub fn read0x3c6(&self) -> u8
+    {
+        return self.vgaPixelMask;
+    }
+
+    // VGA palette read index set
+    pub fn write0x3c7(&mut self,val: u8)
+    {
+        self.vgaPaletteReadColor=val;
+        self.vgaPaletteIndexRGB=0;
+        self.vgaDacReadMode=true;
+    }
+
+    // DAC state: 3 after a read index set, 0 after a write index set
+    pub fn read0x3c7(&self) -> u8
+    {
+        if self.vgaDacReadMode { return 0x03; }
+        return 0x00;
+    }
+
     // VGA palette index set
     pub fn write0x3c8(&mut self,val: u8)
     {
         self.vgaPaletteCurColor=val;
+        self.vgaPaletteIndexRGB=0;
+        self.vgaDacReadMode=false;
+    }
+
+    pub fn read0x3c8(&self) -> u8
+    {
+        return self.vgaPaletteCurColor;
     }
 
-    // VGA rgb value set
+    // VGA rgb value set, the entry changes when its blue component is written
     pub fn write0x3c9(&mut self,val: u8)
     {
-        if self.vgaPaletteIndexRGB==0
-        {
-            self.vgaPalette[self.vgaPaletteCurColor as usize]=(self.vgaPalette[self.vgaPaletteCurColor as usize]&0xffff00)|((val<<2) as u32);
-        }
-        else if self.vgaPaletteIndexRGB==1
-        {
-            self.vgaPalette[self.vgaPaletteCurColor as usize]=(self.vgaPalette[self.vgaPaletteCurColor as usize]&0xff00ff)|(((val<<2) as u32)<<8);
-        }
-        else
+        self.vgaDacLatch[self.vgaPaletteIndexRGB as usize]=val&0x3f;
+
+        self.vgaPaletteIndexRGB+=1;
+        if self.vgaPaletteIndexRGB==3
         {
-            self.vgaPalette[self.vgaPaletteCurColor as usize]=(self.vgaPalette[self.vgaPaletteCurColor as usize]&0x00ffff)|(((val<<2) as u32)<<16);
+            self.setDacEntry(self.vgaPaletteCurColor,self.vgaDacLatch[0],self.vgaDacLatch[1],self.vgaDacLatch[2]);
+            self.vgaPaletteIndexRGB=0;
+            self.vgaPaletteCurColor=self.vgaPaletteCurColor.wrapping_add(1);
         }
+    }
+
+    // VGA rgb value read, from the entry set through 3c7h
+    pub fn read0x3c9(&mut self) -> u8
+    {
+        let (red,green,blue)=self.getDacEntry(self.vgaPaletteReadColor);
+        let retval=if self.vgaPaletteIndexRGB==0 { red } else if self.vgaPaletteIndexRGB==1 { green } else { blue };
 
         self.vgaPaletteIndexRGB+=1;
         if self.vgaPaletteIndexRGB==3
         {
             self.vgaPaletteIndexRGB=0;
-            self.vgaPaletteCurColor+=1;
+            self.vgaPaletteReadColor=self.vgaPaletteReadColor.wrapping_add(1);
         }
+        return retval;
     }
 
     pub fn write0x3ce(&mut self,val: u8)
@@ -600,22 +1677,22 @@ impl vga
         /*
             reg             use
             ----------------------------------------------------------------------
-            0               Set/reset <what I don't know>
-            1               enable set/reset
+            0               Set/reset (color written when enabled in reg 1)
+            1               Enable set/reset
             2               Color compare
-            3               Data rotate value
+            3               Data rotate (bits 0-2 rotate count, bits 3-4 logic op)
             4               Memory plane to read
-            5               Mode register 1
-            6               Mode register 2
-            7               Ignore color compare
-            8               Bit mask for plane change        
+            5               Mode register (bits 0-1 write mode, bit 3 read mode)
+            6               Miscellaneous (bits 2-3 memory map)
+            7               Color don't care
+            8               Bit mask for plane change
         */
         self.egaRegister3ceSelected=val;
     }
 
     pub fn write0x3cf(&mut self,val: u8)
     {
-        self.egaRegister3cfValues[self.egaRegister3ceSelected as usize]=val;
+        self.egaRegister3cfValues[(self.egaRegister3ceSelected&0x0f) as usize]=val;
     }
 
     pub fn write0x3c4(&mut self,val: u8)
@@ -623,36 +1700,265 @@ impl vga
         self.egaRegister3c4Selected=val;
     }
 
-    pub fn write0x3c5(&mut self,val: u8)
-    {
-        self.egaRegister3c5Values[self.egaRegister3c4Selected as usize]=val;
+    pub fn write0x3c5(&mut self,val: u8)
+    {
+        /*
+            reg             use
+            ----------------------------------------------------------------------
+            0               Reset
+            1               Clocking mode (bit 5 = screen off)
+            2               Map mask (planes written by the cpu)
+            3               Character map select
+            4               Memory mode (bit 2 = no odd/even, bit 3 = chain-4)
+        */
+        self.egaRegister3c5Values[(self.egaRegister3c4Selected&0x07) as usize]=val;
+    }
+
+    pub fn read0x3c5(&self) -> u8
+    {
+        return self.egaRegister3c5Values[(self.egaRegister3c4Selected&0x07) as usize];
+    }
+
+    pub fn read0x3cf(&self) -> u8
+    {
+        return self.egaRegister3cfValues[(self.egaRegister3ceSelected&0x0f) as usize];
+    }
+
+    // attribute controller: index and data share the port, a 3dah read resets the flip-flop to index
+    pub fn write0x3c0(&mut self,val:u8)
+    {
+        /*
+            reg             use
+            ----------------------------------------------------------------------
+            00-0f           Palette (pixel value -> DAC index)
+            10              Mode control (bit 3 = blink, bit 5 = panning compat, bit 6 = 256 colors, bit 7 = P5-4 select)
+            11              Overscan color
+            12              Color plane enable
+            13              Horizontal pixel panning
+            14              Color select
+        */
+        if !self.vgaRegister3c0IsData
+        {
+            self.vgaRegister3c0Selected=val&0x3f;
+        }
+        else
+        {
+            let regIdx=(self.vgaRegister3c0Selected&0x1f) as usize;
+            if regIdx<self.vgaRegister3c0Values.len()
+            {
+                self.vgaRegister3c0Values[regIdx]=val;
+                if (regIdx==0x10) && self.isTextMode()
+                {
+                    self.attrBlinkEnabled=(val&0x08)!=0;
+                }
+            }
+        }
+
+        self.vgaRegister3c0IsData=!self.vgaRegister3c0IsData;
+    }
+
+    pub fn read0x3c0(&self) -> u8
+    {
+        return self.vgaRegister3c0Selected;
+    }
+
+    pub fn read0x3c1(&self) -> u8
+    {
+        let regIdx=(self.vgaRegister3c0Selected&0x1f) as usize;
+        if regIdx<self.vgaRegister3c0Values.len()
+        {
+            return self.vgaRegister3c0Values[regIdx];
+        }
+        return 0;
+    }
+
+    // INT 10h,10,3 - BL=0 background intensity, BL=1 blinking
+    pub fn setBlinkEnabled(&mut self,enabled:bool)
+    {
+        self.attrBlinkEnabled=enabled;
+        if enabled { self.vgaRegister3c0Values[0x10]|=0x08; }
+        else { self.vgaRegister3c0Values[0x10]&=!0x08; }
+    }
+
+    pub fn write0x3b8(&mut self,val:u8)
+    {
+        /*
+            |7|6|5|4|3|2|1|0|  3B8 Mode Control Register (MDA/Hercules)
+            | | | | | | `----- 1 = graphics (Hercules, if allowed by 3BF)
+            | | | | `--------- 1 = video enabled
+            | | `------------- 1 = blink enabled
+            `----------------- 1 = display graphics page 1 (Hercules)
+        */
+
+        if !self.isMonoAdapter() { return; }
+
+        self.herculesModeReg=val;
+        self.attrBlinkEnabled=(val&0x20)!=0;
+
+        if self.cardType==machineType::machineHercules
+        {
+            if ((val&0x02)!=0) && ((self.herculesConfigReg&0x01)!=0)
+            {
+                self.mode=0x08;
+            }
+            else
+            {
+                self.mode=0x07;
+            }
+        }
+    }
+
+    pub fn write0x3bf(&mut self,val:u8)
+    {
+        // Hercules config: bit 0 allows graphics mode, bit 1 maps page 1 at b800:0000
+        if self.cardType==machineType::machineHercules
+        {
+            self.herculesConfigReg=val&0x03;
+        }
+    }
+
+    pub fn read0x3ba(&self) -> u8
+    {
+        /*
+            bit 7       Hercules: 0 during vertical retrace (MDA: never changes)
+            bit 3       video signal
+            bit 0       horizontal retrace
+        */
+        let mut retval:u8=((self.scanlineCounter&0x01) as u8)|((((self.scanlineCounter>>1)&0x01) as u8)<<3);
+        if self.cardType==machineType::machineHercules
+        {
+            if self.scanlineCounter<=4000 { retval|=0x80; }
+        }
+        else
+        {
+            retval|=0xf0;
+        }
+
+        return retval;
+    }
+
+    pub fn write0x3d8(&mut self,val:u8)
+    {
+        /*
+            |7|6|5|4|3|2|1|0|  3D8 Mode Select Register
+            | | | | | | | `---- 1 = 80x25 text, 0 = 40x25 text
+            | | | | | | `----- 1 = 320x200 graphics, 0 = text
+            | | | | | `------ 1 = B/W (no color burst)
+            | | | | `------- 1 = video enabled
+            | | | `-------- 1 = 640x200 B/W graphics
+            | | `--------- 1 = blink enabled, 0 = 16 background colors
+            `------------- unused
+        */
+
+        // EGA/VGA and mono cards don't decode 3d8h, their blink bit is in attribute register 10h or 3b8h
+        if self.cardType!=machineType::machineCGA
+        {
+            return;
+        }
+
+        self.attrBlinkEnabled=(val&0x20)!=0;
+        self.cgaModeReg=val;
+
+        // programs that bypass the BIOS switch mode just with this register
+        let newMode;
+        if (val&0x02)!=0
+        {
+            if (val&0x10)!=0 { newMode=0x06; }
+            else if (val&0x04)!=0 { newMode=0x05; }
+            else { newMode=0x04; }
+        }
+        else
+        {
+            let bw=if (val&0x04)!=0 { 0 } else { 1 };
+            if (val&0x01)!=0 { newMode=0x02+bw; }
+            else { newMode=0x00+bw; }
+        }
+
+        self.mode=newMode;
     }
 
     pub fn write0x3d9(&mut self,val:u8)
     {
         /*
             |7|6|5|4|3|2|1|0|  3D9 Color Select Register (3B9 not used)
-            | | | | | `-------- RGB for background
-            | | | | `--------- intensity
-            | | | `---------- unused
-            | | `----------- 1 = palette 1, 0=palette 0 (see below)
-            `-------------- unused
+            | | | | `---------- RGBI: border (text), background (320x200), foreground (640x200)
+            | | | `----------- intensity of the 320x200 palette
+            | | `------------ 1 = palette 1, 0=palette 0 (see below)
+            `--------------- unused
 
             Palette 0 = green, red, brown
             Palette 1 = cyan, magenta, white
+            B/W 320x200 (mode 5) = cyan, red, white
         */        
 
-        if (val&0x20)>0
+        self.cgaColorReg=val&0x3f;
+    }
+
+    // RGBI colors (0-15) of the 4 pixel values in 320x200 mode
+    fn getCgaGraphicsColors(&self) -> [usize;4]
+    {
+        let background=(self.cgaColorReg&0x0f) as usize;
+        let intensity=if (self.cgaColorReg&0x10)!=0 { 8 } else { 0 };
+
+        let colors:[usize;3];
+        if (self.cgaModeReg&0x04)!=0
+        {
+            colors=[3,4,7];
+        }
+        else if (self.cgaColorReg&0x20)!=0
         {
-            self.cgaPaletteSelected=1;
+            colors=[3,5,7];
         }
         else
         {
-            self.cgaPaletteSelected=0;
+            colors=[2,4,6];
+        }
+
+        return [background,colors[0]+intensity,colors[1]+intensity,colors[2]+intensity];
+    }
+
+    // colors of the 4 pixel values in 320x200 mode
+    fn getCgaGraphicsPalette(&self) -> [u32;4]
+    {
+        let colors=self.getCgaGraphicsColors();
+        return [CGA_PALETTE[colors[0]],CGA_PALETTE[colors[1]],CGA_PALETTE[colors[2]],CGA_PALETTE[colors[3]]];
+    }
+
+    // composite signal level of an RGBI color at a half dot of the color clock: chroma 2/3, intensity 1/3
+    fn getCompositeLevel(color:usize,halfDot:usize) -> f32
+    {
+        let hue=color&0x07;
+        let chromaOn=if hue==0 { false }
+                     else if hue==7 { true }
+                     else { ((halfDot+8-CGA_CHROMA_PHASE[hue])%8)<4 };
+
+        let mut level:f32=if chromaOn { 2.0/3.0 } else { 0.0 };
+        if (color&0x08)!=0 { level+=1.0/3.0; }
+        return level;
+    }
+
+    // NTSC decoding of a color clock: luma is the average, I and Q are the signal at the 2 chroma phases
+    fn decodeCompositeClock(signal:&[f32;8],cosTable:&[f32;8],sinTable:&[f32;8]) -> u32
+    {
+        let mut luma:f32=0.0;
+        let mut chromaI:f32=0.0;
+        let mut chromaQ:f32=0.0;
+        for k in 0..8
+        {
+            luma+=signal[k]/8.0;
+            chromaI+=signal[k]*cosTable[k];
+            chromaQ+=signal[k]*sinTable[k];
         }
+
+        let r=luma+(0.956*chromaI)+(0.621*chromaQ);
+        let g=luma-(0.272*chromaI)-(0.647*chromaQ);
+        let b=luma-(1.106*chromaI)+(1.703*chromaQ);
+
+        let toByte=|c:f32| -> u32 { (c.clamp(0.0,1.0).powf(CGA_COMPOSITE_GAMMA)*255.0).round() as u32 };
+        return (toByte(r)<<16)|(toByte(g)<<8)|toByte(b);
     }
 
-    pub fn read0x3da(&self) -> u8
+    pub fn read0x3da(&mut self) -> u8
     {
         // CGA status register	EGA/VGA: input status 1 register
         /*
@@ -664,6 +1970,8 @@ impl vga
             = 1   memory access without interfering with display
         */          
 
+        self.vgaRegister3c0IsData=false;
+
         let mut retval:u8=0;
         if self.scanlineCounter>4000
         {
@@ -703,104 +2011,475 @@ impl vga
     {
         self.scanlineCounter+=1;
         self.scanlineCounter%=4166; // 6hz, 250.000ips
+
+        // a new frame starts, the one just displayed goes to the video
+        if self.scanlineCounter==0
+        {
+            self.frameCounter+=1;
+            if self.recorder.isRecording()
+            {
+                self.recordFrame();
+            }
+        }
+    }
+
+    fn recordFrame(&mut self)
+    {
+        let mut frame=videoFrame::new();
+        self.fbTobuf32(&mut frame);
+        self.recorder.addFrame(&frame);
+    }
+
+    pub fn startRecording(&mut self,fname:&str)
+    {
+        if self.recorder.start(fname)
+        {
+            emuLog::trace(&format!("Recording video to {}",fname));
+        }
+    }
+
+    pub fn stopRecording(&mut self)
+    {
+        if self.recorder.isRecording()
+        {
+            self.recorder.stop();
+            emuLog::trace(&format!("Video {} saved, {} frames",self.recorder.fileName,self.recorder.frameCount));
+        }
     }
 
     //
     // blitting
     //
 
-    pub fn fbTobuf32(&mut self,gui:&mut guiif)
+    // MDA attributes: (foreground, background, underline)
+    fn getMdaColors(&self,attributes:u8,blinkOn:bool) -> (u32,u32,bool)
     {
-        if self.mode!=gui.videoMode.into()
+        let normal=0xaaaaaa;
+        let bright=0xffffff;
+
+        let fgBits=attributes&0x07;
+        let bgBits=(attributes>>4)&0x07;
+        let mut fgCol=if (attributes&0x08)!=0 { bright } else { normal };
+        let mut bgCol=0;
+        let mut underline=false;
+
+        if (fgBits==0) && (bgBits==7)
         {
-            return;
+            // reverse video
+            fgCol=0;
+            bgCol=normal;
+            if (!self.attrBlinkEnabled) && ((attributes&0x80)!=0) { bgCol=bright; }
+        }
+        else if (fgBits==0) && (bgBits==0)
+        {
+            // non display
+            fgCol=0;
+        }
+        else if fgBits==1
+        {
+            underline=true;
+        }
+
+        if self.attrBlinkEnabled && ((attributes&0x80)!=0) && (!blinkOn)
+        {
+            fgCol=bgCol;
+            underline=false;
         }
 
-        let cgaPalette = Vec::from([0x000000,0x55ff55,0xff5555,0xffff55]);
-        let cgaPalette2 = Vec::from([0x000000,0x55ffff,0xff55ff,0xffffff]);
+        return (fgCol,bgCol,underline);
+    }
 
-        if self.mode==0x13
+    fn drawTextCursor(&mut self,startOffset:usize,cols:u32,rows:u32,resx:u32,fontHeight:u32)
+    {
+        // CRTC 0ah bits 5-6: 01 no cursor, 11 blink at 1/32 of the frame rate, else 1/16
+        let cursorStart=self.egaRegister3b5Values[0x0a];
+        let cursorEnd=self.egaRegister3b5Values[0x0b]&0x1f;
+        let blinkMode=(cursorStart>>5)&0x03;
+        if blinkMode==1 { return; }
+
+        let framesPerCycle=if blinkMode==3 { 32 } else { 16 };
+        if !self.getBlinkPhase(framesPerCycle) { return; }
+
+        let startLine=cursorStart&0x1f;
+        if startLine>cursorEnd { return; }
+
+        let cursorAddr=((((self.egaRegister3b5Values[0x0e] as usize)<<8)|(self.egaRegister3b5Values[0x0f] as usize))*2)&0x7fff;
+        let cellIdx=(cursorAddr.wrapping_sub(startOffset)&0x7fff)/2;
+        if cellIdx>=((cols*rows) as usize) { return; }
+
+        // cursor scanlines are in CRTC char height units, scaled to the font height
+        let charHeight=((self.egaRegister3b5Values[0x09]&0x1f) as u32)+1;
+        let fromLine=((startLine as u32)*fontHeight)/charHeight;
+        let toLine=std::cmp::min(((((cursorEnd as u32)+1)*fontHeight)/charHeight).max(fromLine+1),fontHeight);
+
+        // the cursor takes the foreground color of the char below
+        let attributes=self.cgaFramebuffer[((startOffset+(cellIdx*2))&0x7fff)+1];
+        let curColor=if self.mode==0x07 { self.getMdaColors(attributes,true).0 } else { self.getTextColor(attributes&0x0f) };
+
+        let dstx=((cellIdx as u32)%cols)*9;
+        let dsty=((cellIdx as u32)/cols)*fontHeight;
+        for y in fromLine..toLine
         {
-            let mut idx:usize=0;
-            for i in gui.frameBuffer.iter_mut() 
+            for x in 0..9
+            {
+                self.tempFramebuf[(dstx+x+((dsty+y)*resx)) as usize]=curColor;
+            }
+        }
+    }
+
+    // scanlines per displayed row: CRTC 09h max scanline, plus bit 7 double scanning
+    fn getScanlinesPerRow(&self) -> usize
+    {
+        let maxScanline=self.egaRegister3b5Values[0x09];
+        let mut linesPerRow=((maxScanline&0x1f) as usize)+1;
+        if (maxScanline&0x80)!=0 { linesPerRow*=2; }
+        return linesPerRow;
+    }
+
+    // screen resolution as programmed in the CRTC, used by the text and VGA graphics modes (tweaked ones too)
+    pub fn getCrtcResolution(&self) -> Option<(u32,u32)>
+    {
+        if self.isTextMode()
+        {
+            return Some(self.getTextResolution());
+        }
+        else if !self.isVgaGraphicsMode()
+        {
+            return None;
+        }
+
+        let crtc=&self.egaRegister3b5Values;
+        let charClocks=(crtc[0x01] as u32)+1;
+        let resx=if (self.vgaRegister3c0Values[0x10]&0x40)!=0 { charClocks*4 } else { charClocks*8 };
+
+        let vertDisplayEnd=(crtc[0x12] as u32)|(((crtc[0x07] as u32)&0x02)<<7)|(((crtc[0x07] as u32)&0x40)<<3);
+        let resy=(vertDisplayEnd+1)/(self.getScanlinesPerRow() as u32);
+
+        // don't follow half programmed CRTCs
+        if (resx<160) || (resx>1024) || (resy<100) || (resy>768)
+        {
+            return None;
+        }
+
+        return Some((resx,resy));
+    }
+
+    // size of the rendered frame: the CRTC programmed one, else the standard size of the mode
+    pub fn getVideoResolution(&self) -> (u32,u32)
+    {
+        if let Some(crtcSize)=self.getCrtcResolution() { return crtcSize; }
+        if let Some(vesaSize)=vga::getVesaResolution(self.mode) { return vesaSize; }
+
+        if (self.mode==0x04) || (self.mode==0x05) || (self.mode==0x0d) || (self.mode==0x13) { return (320,200); }
+        else if (self.mode==0x06) || (self.mode==0x0e) { return (640,200); }
+        else if self.mode==0x08 { return (720,348); }
+        else if (self.mode==0x0f) || (self.mode==0x10) { return (640,350); }
+        else if (self.mode==0x11) || (self.mode==0x12) { return (640,480); }
+        return (720,400);
+    }
+
+    // 4 bit pixel value -> attribute palette -> DAC
+    fn getAttributeColor(&self,pixelVal:u8) -> u32
+    {
+        let attrModeCtrl=self.vgaRegister3c0Values[0x10];
+        let colorSelect=self.vgaRegister3c0Values[0x14];
+        let palEntry=self.vgaRegister3c0Values[(pixelVal&0x0f) as usize];
+
+        let dacIdx;
+        if (attrModeCtrl&0x80)!=0
+        {
+            dacIdx=(palEntry&0x0f)|((colorSelect&0x0f)<<4);
+        }
+        else
+        {
+            dacIdx=(palEntry&0x3f)|((colorSelect&0x0c)<<4);
+        }
+
+        return self.getDacColor(dacIdx);
+    }
+
+    /*
+        border color: on the CGA it's the color select register low nibble (the 640x200 mode has it as
+        foreground, with a black border), MDA and Hercules have none, the VGA overscan register (attribute 11h)
+        goes straight to the DAC
+    */
+    pub fn getOverscanColor(&self) -> u32
+    {
+        if self.cardType==machineType::machineCGA
+        {
+            if self.mode==0x06 { return 0; }
+            return CGA_PALETTE[(self.cgaColorReg&0x0f) as usize];
+        }
+        else if (self.cardType==machineType::machineMDA) || (self.cardType==machineType::machineHercules)
+        {
+            return 0;
+        }
+
+        return self.getDacColor(self.vgaRegister3c0Values[0x11]);
+    }
+
+    fn getDacColor(&self,dacIdx:u8) -> u32
+    {
+        return self.vgaPalette[(dacIdx&self.vgaPixelMask) as usize];
+    }
+
+    // text colors go through the attribute controller on the VGA, the 6845 cards have the fixed RGBI ones
+    fn getTextColor(&self,colorIdx:u8) -> u32
+    {
+        if self.hasMc6845() { return CGA_PALETTE[(colorIdx&0x0f) as usize]; }
+        return self.getAttributeColor(colorIdx&self.vgaRegister3c0Values[0x12]);
+    }
+
+    // register driven VGA graphics: 16 colors planar, or 256 colors either chained (mode 13h) or unchained (mode X)
+    fn renderVgaGraphics(&mut self,frame:&mut videoFrame)
+    {
+        let resx=frame.width as usize;
+        let resy=frame.height as usize;
+
+        let crtc=&self.egaRegister3b5Values;
+        let attrModeCtrl=self.vgaRegister3c0Values[0x10];
+        let is256colors=(attrModeCtrl&0x40)!=0;
+        let screenOff=(self.egaRegister3c5Values[1]&0x20)!=0;
+        let colorPlaneEnable=self.vgaRegister3c0Values[0x12]&0x0f;
+
+        let startAddr=((crtc[0x0c] as usize)<<8)|(crtc[0x0d] as usize);
+        let lineStride=(crtc[0x13] as usize)*2;
+        let lineCompare=(crtc[0x18] as usize)|(((crtc[0x07] as usize)&0x10)<<4)|(((crtc[0x09] as usize)&0x40)<<3);
+        let linesPerRow=self.getScanlinesPerRow();
+
+        let pelPanning=(self.vgaRegister3c0Values[0x13]&0x07) as usize;
+        let mut panning=if is256colors { pelPanning>>1 } else { pelPanning };
+
+        let mut rowAddr=startAddr;
+        let mut splitDone=false;
+        let mut idx:usize=0;
+        for y in 0..resy
+        {
+            // split screen: after the line compare scanline, display restarts from address 0
+            if (!splitDone) && ((y*linesPerRow)>lineCompare)
+            {
+                splitDone=true;
+                rowAddr=0;
+                if (attrModeCtrl&0x20)!=0 { panning=0; }
+            }
+
+            for x in 0..resx
             {
-                if idx<65536
+                if idx>=frame.pixels.len() { return; }
+
+                let px=x+panning;
+                let color;
+                if screenOff
+                {
+                    color=0;
+                }
+                else if is256colors
+                {
+                    let pixelVal=self.framebuffer[planeIndex(rowAddr+(px>>2),px&0x03)];
+                    color=self.getDacColor(pixelVal);
+                }
+                else
                 {
-                    let bufVal=self.framebuffer[idx];
-                    *i = self.vgaPalette[bufVal as usize];
+                    let planeOffs=rowAddr+(px>>3);
+                    let curbit=7-(px&0x07);
+                    let mut pixelVal:u8=0;
+                    for plane in 0..4
+                    {
+                        pixelVal|=((self.framebuffer[planeIndex(planeOffs,plane)]>>curbit)&0x01)<<plane;
+                    }
+                    color=self.getAttributeColor(pixelVal&colorPlaneEnable);
                 }
+
+                frame.pixels[idx]=color;
                 idx+=1;
-            }        
+            }
+
+            rowAddr+=lineStride;
         }
-        else if (self.mode==0x00) || (self.mode==0x01) || (self.mode==0x02)  || (self.mode==0x03)
+    }
+
+    /*
+        composite monitor: the 640 dots of a CGA line are a NTSC signal, every 4 dots (a color clock)
+        are decoded to one artifact color. Each dot carries the signal of its color as selected by
+        3d9h: foreground or black in 640x200, the 4 colors palette in 320x200 where a pixel is 2 dots
+    */
+    fn renderCgaComposite(&mut self,frame:&mut videoFrame)
+    {
+        let resx=frame.width as usize;
+        let pixelsPerClock=resx/160;
+        let hiRes=self.mode==0x06;
+        let foreground=(self.cgaColorReg&0x0f) as usize;
+        let palette=self.getCgaGraphicsColors();
+        // 3d8h bit 2 turns off the color burst, the monitor shows just the luma (BIOS modes 5 and 6)
+        let saturation=if (self.cgaModeReg&0x04)!=0 { 0.0 } else { CGA_COMPOSITE_SATURATION };
+
+        let mut cosTable:[f32;8]=[0.0;8];
+        let mut sinTable:[f32;8]=[0.0;8];
+        for k in 0..8
         {
-            // mode 0,1 - 40x25 text mode, 9x16 chars, 360x400
-            // mode 2 - 80x25 text mode, 9x16 chars, 720x400
+            let angle=((k as f32)*std::f32::consts::PI/4.0)+CGA_COMPOSITE_HUE;
+            cosTable[k]=angle.cos()*saturation/4.0;
+            sinTable[k]=angle.sin()*saturation/4.0;
+        }
+
+        for y in 0..200
+        {
+            let lineOffs=((y&1)*0x2000)+((y>>1)*80);
+            for clock in 0..160
+            {
+                let theByte=self.cgaFramebuffer[lineOffs+(clock>>1)];
+                let nibble=if (clock&1)==0 { theByte>>4 } else { theByte&0x0f };
 
-            let mut resx=720; let mut _resy=400; let mut cols=80; let mut rows=25;
-            if (self.mode==0x00) || (self.mode==0x01)
-            { 
-                resx=360; _resy=400; cols=40; rows=25; 
+                let mut signal:[f32;8]=[0.0;8];
+                for dot in 0..4
+                {
+                    let dotColor=if hiRes { if ((nibble>>(3-dot))&0x01)!=0 { foreground } else { 0 } }
+                                 else { palette[((nibble>>(2-((dot>>1)*2)))&0x03) as usize] };
+                    signal[dot*2]=vga::getCompositeLevel(dotColor,dot*2);
+                    signal[(dot*2)+1]=vga::getCompositeLevel(dotColor,(dot*2)+1);
+                }
+
+                let color=vga::decodeCompositeClock(&signal,&cosTable,&sinTable);
+                for px in 0..pixelsPerClock
+                {
+                    frame.pixels[(y*resx)+(clock*pixelsPerClock)+px]=color;
+                }
+            }
+        }
+    }
+
+    pub fn fbTobuf32(&mut self,frame:&mut videoFrame)
+    {
+        frame.fitVideomode(self);
+        frame.borderColor=self.getOverscanColor();
+
+        // CGA with video disabled from 3d8h
+        if (self.cardType==machineType::machineCGA) && ((self.cgaModeReg&0x08)==0) && (self.mode<=0x06)
+        {
+            for i in frame.pixels.iter_mut() 
+            {
+                *i=0;
             }
+            return;
+        }
 
-            let mut bufIdx=0;
+        if self.isVgaGraphicsMode()
+        {
+            self.renderVgaGraphics(frame);
+        }
+        else if self.compositeOutput && (self.cardType==machineType::machineCGA) && ((self.mode==0x04) || (self.mode==0x05) || (self.mode==0x06))
+        {
+            self.renderCgaComposite(frame);
+        }
+        else if self.isVesaMode()
+        {
+            // SVGA 256 colors, linear
+            let mut idx:usize=0;
+            for i in frame.pixels.iter_mut() 
+            {
+                *i=self.getDacColor(self.framebuffer[idx]);
+                idx+=1;
+            }
+        }
+        else if (self.mode==0x00) || (self.mode==0x01) || (self.mode==0x02)  || (self.mode==0x03)
+        {
+            // color text, as many rows and columns as the CRTC says
+            // the 9x16 font is 720x400 for 80x25, 80x50 uses the 8x8 font and 160x100 2 scanlines cells
+            let (cols,rows,charHeight)=self.getTextGeometry();
+            let cellHeight=charHeight*self.getTextLineScale();
+            let resx=cols*9;
+
+            let startOffset=self.getDisplayStartOffset();
+            let blinkOn=self.getBlinkPhase(32);
             for i in 0..rows*cols
             {
+                let bufIdx=(startOffset+(i*2))&0x7fff;
                 let attributes:u8=self.cgaFramebuffer[bufIdx+1];
-                let fgCol=attributes&0x0f;
-                let bgCol=(attributes>>4)&0x07;
+                let mut fgCol=attributes&0x0f;
+                let bgCol;
+                if self.attrBlinkEnabled
+                {
+                    // bit 7 blinks the char
+                    bgCol=(attributes>>4)&0x07;
+                    if ((attributes&0x80)!=0) && (!blinkOn) { fgCol=bgCol; }
+                }
+                else
+                {
+                    // bit 7 is background intensity
+                    bgCol=(attributes>>4)&0x0f;
+                }
                 let charNum:u8=self.cgaFramebuffer[bufIdx];
-                let tempVec:Vec<u8>=Vec::new();
-                self.drawCharOnScreen
-                (
-                    9,16,
-                    32,
-                    charNum as u32,
-                    i/cols,
-                    i%cols,
-                    resx,
-                    self.vgaPalette[fgCol as usize],self.vgaPalette[bgCol as usize],tempVec);
-                bufIdx+=2;
+                let fontBase=self.getCharMapBase(attributes);
+                self.drawTextChar(charNum,fontBase,i/cols,i%cols,cellHeight,resx,
+                    self.getTextColor(fgCol),self.getTextColor(bgCol));
             }
 
+            self.drawTextCursor(startOffset,cols as u32,rows as u32,resx as u32,cellHeight as u32);
+
             let mut idx:usize=0;
-            for i in gui.frameBuffer.iter_mut() 
+            for i in frame.pixels.iter_mut() 
             {
                 let bufVal=self.tempFramebuf[idx];
                 *i = bufVal;
                 idx+=1;
             }        
         }
-        else if (self.mode==0x0d) || (self.mode==0x10)
+        else if self.mode==0x07
         {
-            // EGA 320x200x16
-            // EGA 640x350x16
-            let mut idx:usize=0;
-            let mut curbit=7;
-
-            let mut planeSize=0x2000;
-            if self.mode==0x10 { planeSize=0x8000; }
-            
-            for i in gui.frameBuffer.iter_mut() 
+            // MDA text mode, 9x14 chars, 80x25 is 720x350
+            let (cols,rows,charHeight)=self.getTextGeometry();
+            let resx=cols*9;
+            let ulineRow=std::cmp::min(12,charHeight-1);
+
+            let videoOn=(self.herculesModeReg&0x08)!=0;
+            let startOffset=self.getDisplayStartOffset();
+            let blinkOn=self.getBlinkPhase(32);
+            for i in 0..rows*cols
             {
-                {
-                    let p0=(self.framebuffer[idx]&(1<<curbit))>>curbit;
-                    let p1=(self.framebuffer[idx+planeSize]&(1<<curbit))>>curbit;
-                    let p2=(self.framebuffer[idx+(planeSize*2)]&(1<<curbit))>>curbit;
-                    let p3=(self.framebuffer[idx+(planeSize*3)]&(1<<curbit))>>curbit;
-
-                    let colidx=p0|(p1<<1)|(p2<<2)|(p3<<3);
-
-                    *i = self.vgaPalette[colidx as usize];
+                let bufIdx=(startOffset+(i*2))&0x0fff;
+                let attributes:u8=self.cgaFramebuffer[bufIdx+1];
+                let charNum:u8=self.cgaFramebuffer[bufIdx];
+                let (mut fgCol,bgCol,underline)=self.getMdaColors(attributes,blinkOn);
+                if !videoOn { fgCol=0; }
+                let fontBase=self.getCharMapBase(attributes);
+                self.drawTextChar(charNum,fontBase,i/cols,i%cols,charHeight,resx,fgCol,if videoOn { bgCol } else { 0 });
 
-                    curbit-=1;
-                    if curbit<0
+                if underline
+                {
+                    let ulineStart=((((i/cols)*charHeight)+ulineRow)*resx)+((i%cols)*9);
+                    for x in 0..9
                     {
-                        curbit=7;
-                        idx+=1;
+                        self.tempFramebuf[ulineStart+x]=fgCol;
                     }
                 }
-            }               
+            }
+
+            if videoOn
+            {
+                self.drawTextCursor(startOffset,cols as u32,rows as u32,resx as u32,charHeight as u32);
+            }
+
+            let mut idx:usize=0;
+            for i in frame.pixels.iter_mut() 
+            {
+                *i=self.tempFramebuf[idx];
+                idx+=1;
+            }        
+        }
+        else if self.mode==0x08
+        {
+            // Hercules 720x348, 4 interleaved banks of 90 bytes lines
+            let pageBase=if (self.herculesModeReg&0x80)!=0 { 0x8000 } else { 0 };
+            let videoOn=(self.herculesModeReg&0x08)!=0;
+            let mut idx:usize=0;
+            for i in frame.pixels.iter_mut() 
+            {
+                let x=idx%720;
+                let y=idx/720;
+                let theByte=self.cgaFramebuffer[pageBase+(0x2000*(y&3))+(90*(y>>2))+(x>>3)];
+                if videoOn && ((theByte&(0x80>>(x&7)))!=0) { *i=0xffffff; } else { *i=0; }
+                idx+=1;
+            }
         }
         else if self.mode==0x04 || self.mode==0x05
         {
@@ -811,11 +2490,10 @@ impl vga
             let mut fbidx=0;
             let mut shifter=6;
 
-            let mut cgaPal=cgaPalette;
-            if self.mode==0x05 || self.cgaPaletteSelected==1 { cgaPal=cgaPalette2; }
+            let cgaPal=self.getCgaGraphicsPalette();
 
             // even rows
-            for pix in gui.frameBuffer.iter_mut()
+            for pix in frame.pixels.iter_mut()
             {
                 let theByte=self.cgaFramebuffer[adder+fbidx];
                 let b0:usize=((theByte>>shifter)&0x03) as usize;
@@ -844,7 +2522,7 @@ impl vga
             shifter=6;
 
             // odd rows
-            for pix in gui.frameBuffer.iter_mut()
+            for pix in frame.pixels.iter_mut()
             {
                 let theByte=self.cgaFramebuffer[adder+fbidx];
                 let b0:usize=((theByte>>shifter)&0x03) as usize;
@@ -873,13 +2551,14 @@ impl vga
             let mut curbyte=0;
             let mut fbidx=0;
             let mut shifter=7;
+            let fgColor=CGA_PALETTE[(self.cgaColorReg&0x0f) as usize];
 
             // even rows
-            for pix in gui.frameBuffer.iter_mut()
+            for pix in frame.pixels.iter_mut()
             {
                 let theByte=self.cgaFramebuffer[adder+fbidx];
                 let b0:usize=((theByte>>shifter)&0x01) as usize;
-                if adder==0 { if b0>0 { *pix=0xffffff; } else { *pix=0; } }
+                if adder==0 { if b0>0 { *pix=fgColor; } else { *pix=0; } }
                 shifter-=1;
 
                 if shifter<0
@@ -901,14 +2580,14 @@ impl vga
             currow=0;
             curbyte=0;
             fbidx=0;
-            shifter=6;
+            shifter=7;
 
             // odd rows
-            for pix in gui.frameBuffer.iter_mut()
+            for pix in frame.pixels.iter_mut()
             {
                 let theByte=self.cgaFramebuffer[adder+fbidx];
                 let b0:usize=((theByte>>shifter)&0x01) as usize;
-                if adder==0x2000 { if b0>0 { *pix=0xffffff; } else { *pix=0; } }
+                if adder==0x2000 { if b0>0 { *pix=fgColor; } else { *pix=0; } }
                 shifter-=1;
 
                 if shifter<0
@@ -928,15 +2607,14 @@ impl vga
         }
     }
 
-    pub fn new(font9x16:&str,font8x8:&str) -> Self 
+    pub fn new(font9x16:&str,font8x8:&str,cardType:machineType) -> Self 
     {
         // load fonts
 
         let fFont9x16 = match image::open(font9x16) {
             Ok(f) => f,
             Err(_e) => {
-                println!("Unable to open file {}",font9x16);
-                process::exit(0x0100);
+                emuLog::fatalError(&format!("Unable to open file {}",font9x16),emuLog::EXIT_ABORTED);
             }
         };
 
@@ -969,8 +2647,7 @@ impl vga
         let fFont8x8 = match image::open(font8x8) {
             Ok(f) => f,
             Err(_e) => {
-                println!("Unable to open file {}",font8x8);
-                process::exit(0x0100);
+                emuLog::fatalError(&format!("Unable to open file {}",font8x8),emuLog::EXIT_ABORTED);
             }
         };
 
@@ -993,79 +2670,88 @@ impl vga
             font8x8vec.push(newLine);
         }
 
+        // ROM fonts, one byte per scanline with bit 7 as the leftmost dot
+        // 8x16 is the 9x16 font without the 9th column, 8x14 is the 8x16 without the top and bottom scanlines
+        let mut romFont8x16:Vec<u8>=Vec::new();
+        let mut romFont8x14:Vec<u8>=Vec::new();
+        let mut romFont8x8:Vec<u8>=Vec::new();
+        for charNum in 0..256
+        {
+            let srcx=(charNum%32)*9;
+            let srcy=(charNum/32)*16;
+            for y in 0..16
+            {
+                let mut glyphLine:u8=0;
+                for x in 0..8
+                {
+                    if font9x16vec[srcy+y][srcx+x]!=0 { glyphLine|=0x80>>x; }
+                }
+                romFont8x16.push(glyphLine);
+                if (y>=1) && (y<15) { romFont8x14.push(glyphLine); }
+            }
+
+            let srcx=(charNum%32)*8;
+            let srcy=(charNum/32)*8;
+            for y in 0..8
+            {
+                let mut glyphLine:u8=0;
+                for x in 0..8
+                {
+                    if font8x8vec[srcy+y][srcx+x]!=0 { glyphLine|=0x80>>x; }
+                }
+                romFont8x8.push(glyphLine);
+            }
+        }
+
         // framebuffers
 
         let fbSize=65536*6; // >256k
-        let mut vgaFramebuf:Vec<u8>=Vec::with_capacity(fbSize);
+        let mut vgaFramebuf:Vec<u8>=Vec::with_capacity(VGA_MEMORY_SIZE);
         let mut cgaFramebuf:Vec<u8>=Vec::with_capacity(fbSize);
         let mut tmpFramebuf:Vec<u32>=Vec::with_capacity(fbSize);
-        for _i in 0..fbSize
+        for _i in 0..VGA_MEMORY_SIZE
         {
             vgaFramebuf.push(0);
+        }
+        for _i in 0..fbSize
+        {
             cgaFramebuf.push(0);
             tmpFramebuf.push(0);
         }
 
-        let defaultVgaPalette = Vec::from(
-            [
-                0x000000,0x0000aa,0x00aa00,0x00aaaa,0xaa0000,0xaa00aa,0xaa5500,0xaaaaaa,
-                0x555555,0x5555ff,0x55ff55,0x55ffff,0xff5555,0xff55ff,0xffff55,0xffffff,
-                0x000000,0x141414,0x202020,0x2c2c2c,0x383838,0x454545,
-                0x515151,0x616161,0x717171,0x828282,0x929292,0xa2a2a2,0xb6b6b6,0xcbcbcb,
-                0xe3e3e3,0xffffff,0x0000ff,0x4100ff,0x7d00ff,0xbe00ff,0xff00ff,0xff00be,
-                0xff007d,0xff0041,0xff0000,0xff4100,0xff7d00,0xffbe00,0xffff00,0xbeff00,
-                0x7dff00,0x41ff00,0x00ff00,0x00ff41,0x00ff7d,0x00ffbe,0x00ffff,0x00beff,
-                0x007dff,0x0041ff,0x7d7dff,0x9e7dff,0xbe7dff,0xdf7dff,0xff7dff,0xff7ddf,
-                0xff7dbe,0xff7d9e,0xff7d7d,0xff9e7d,0xffbe7d,0xffdf7d,0xffff7d,
-                0xdfff7d,0xbeff7d,0x9eff7d,0x7dff7d,0x7dff9e,0x7dffbe,0x7dffdf,0x7dffff,
-                0x7ddfff,0x7dbeff,0x7d9eff,0xb6b6ff,0xc7b6ff,0xdbb6ff,0xebb6ff,0xffb6ff,
-                0xffb6eb,0xffb6db,0xffb6c7,0xffb6b6,0xffc7b6,0xffdbb6,0xffebb6,0xffffb6,
-                0xebffb6,0xdbffb6,0xc7ffb6,0xb6ffb6,0xb6ffc7,0xb6ffdb,0xb6ffeb,
-                0xb6ffff,0xb6ebff,0xb6dbff,0xb6c7ff,0x000071,0x1c0071,0x380071,0x550071,
-                0x710071,0x710055,0x710038,0x71001c,0x710000,0x711c00,0x713800,0x715500,
-                0x717100,0x557100,0x387100,0x1c7100,0x007100,0x00711c,0x007138,0x007155,
-                0x007171,0x005571,0x003871,0x001c71,0x383871,0x453871,0x553871,0x613871,
-                0x713871,0x713861,0x713855,0x713845,0x713838,0x714538,0x715538,0x716138,
-                0x717138,0x617138,0x557138,0x457138,0x387138,0x387145,0x387155,0x387161,
-                0x387171,0x386171,0x385571,0x384571,0x515171,0x595171,0x615171,0x695171,
-                0x715171,0x715169,0x715161,0x715159,0x715151,0x715951,0x716151,0x716951,
-                0x717151,0x697151,0x617151,0x597151,0x517151,0x517159,0x517161,0x517169,
-                0x517171,0x516971,0x516171,0x515971,0x000041,0x100041,0x200041,0x300041,
-                0x410041,0x410030,0x410020,0x410010,0x410000,0x411000,0x412000,0x413000,
-                0x414100,0x304100,0x204100,0x104100,0x004100,0x004110,0x004120,0x004130,
-                0x004141,0x003041,0x002041,0x001041,0x202041,0x282041,0x302041,0x382041,
-                0x412041,0x412038,0x412030,0x412028,0x412020,0x412820,0x413020,0x413820,
-                0x414120,0x384120,0x304120,0x284120,0x204120,0x204128,0x204130,0x204138,
-                0x204141,0x203841,0x203041,0x202841,0x2c2c41,0x302c41,0x342c41,0x3c2c41,
-                0x412c41,0x412c3c,0x412c34,0x412c30,0x412c2c,0x41302c,0x41342c,0x413c2c,
-                0x41412c,0x3c412c,0x34412c,0x30412c,0x2c412c,0x2c4130,0x2c4134,0x2c413c,
-                0x2c4141,0x2c3c41,0x2c3441,0x2c3041,0x000000,0x000000,0x000000,0x000000,
-                0x000000,0x000000,0x000000,0x000000
-                ]
-        );
-
-        let reg3c5Values=Vec::from([0,0,0,0,0]); // 5 registers
-        let reg3cfValues=Vec::from([0,0,0,0,0,0,0,0,0]); // 9 registers
+
+        let reg3c5Values=Vec::from([0;8]); // 5 registers, index is 3 bits wide
+        let reg3cfValues=Vec::from([0;16]); // 9 registers, index is 4 bits wide
+        let reg3c0Values=Vec::from([0;0x15]); // 21 registers
         let reg3b5Values=Vec::from([0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]); // 64 registers for vga
         let latches=Vec::from([0,0,0,0]);
 
-        vga
+        let isMono=(cardType==machineType::machineMDA) || (cardType==machineType::machineHercules);
+        let powerOnMode=if isMono { 7 } else { 2 };
+
+        let mut newVga=vga
         {
-            mode: 2,
+            mode: powerOnMode,
             framebuffer: vgaFramebuf,
             cgaFramebuffer: cgaFramebuf,
             tempFramebuf: tmpFramebuf,
-            font9x16data: font9x16vec,
             font9x16width: img_width,
             font9x16height: img_height,
+            romFont8x16: romFont8x16,
+            romFont8x14: romFont8x14,
+            romFont8x8: romFont8x8,
             font8x8data: font8x8vec,
             font8x8width: img_width8,
             font8x8height: img_height8,
-            cursorx:0,
-            cursory:0,
-            vgaPalette: defaultVgaPalette,
+            pageCursors: Vec::from([(0,0);16]),
+            activePage: 0,
+            vgaPalette: Vec::from(VGA_DEFAULT_PALETTE),
             vgaPaletteCurColor: 0,
             vgaPaletteIndexRGB: 0,
+            vgaPaletteReadColor: 0,
+            vgaDacReadMode: false,
+            vgaDacLatch: Vec::from([0,0,0]),
+            vgaPixelMask: 0xff,
             egaRegister3c4Selected: 0,
             egaRegister3c5Values: reg3c5Values,
             egaRegister3ceSelected: 0,
@@ -1073,8 +2759,189 @@ impl vga
             egaRegister3b4Selected: 0,
             egaRegister3b5Values: reg3b5Values,
             egaDataLatch: latches,
+            vgaRegister3c0Selected: 0x20,
+            vgaRegister3c0Values: reg3c0Values,
+            vgaRegister3c0IsData: false,
             scanlineCounter: 0,
-            cgaPaletteSelected: 0
+            frameCounter: 0,
+            cgaModeReg: 0x29,
+            cgaColorReg: 0,
+            attrBlinkEnabled: true,
+            cardType: cardType,
+            herculesModeReg: 0x28,
+            herculesConfigReg: 0,
+            vesaWindowPos: 0,
+            compositeOutput: false,
+            textScanlines: 400,
+            graphicsCharHeight: 8,
+            graphicsTextRows: 25,
+            recorder: videoRecorder::new()
+        };
+
+        // power on text mode registers
+        newVga.loadModeRegisters(powerOnMode);
+        return newVga;
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    // EGA in a planar 16 colors mode: a000:0000 mapped, all planes enabled, no odd/even
+    fn newPlanarEga() -> vga
+    {
+        let fontDir=concat!(env!("CARGO_MANIFEST_DIR"),"/fonts");
+        let mut card=vga::new(&format!("{}/9x16.png",fontDir),&format!("{}/cga8.png",fontDir),machineType::machineEGA);
+        card.egaRegister3c5Values[2]=0x0f;
+        card.egaRegister3c5Values[4]=0x06;
+        card.egaRegister3cfValues=Vec::from([0;16]);
+        card.egaRegister3cfValues[6]=0x05;
+        card.egaRegister3cfValues[8]=0xff;
+        return card;
+    }
+
+    fn planeBytes(card:&vga,offs:usize) -> [u8;4]
+    {
+        return [card.framebuffer[planeIndex(offs,0)],card.framebuffer[planeIndex(offs,1)],
+                card.framebuffer[planeIndex(offs,2)],card.framebuffer[planeIndex(offs,3)]];
+    }
+
+    fn setPlaneBytes(card:&mut vga,offs:usize,vals:[u8;4])
+    {
+        for plane in 0..4
+        {
+            card.framebuffer[planeIndex(offs,plane)]=vals[plane];
+        }
+    }
+
+    #[test]
+    fn writeMode0SetResetMapMaskAndBitMask()
+    {
+        let mut card=newPlanarEga();
+        card.egaRegister3cfValues[0]=0x05;
+        card.egaRegister3cfValues[1]=0x03;
+        card.writeMemory(0xa0000,0xaa);
+        assert_eq!(planeBytes(&card,0),[0xff,0x00,0xaa,0xaa]);
+
+        // planes off in the map mask keep their value, bits off in the bit mask come from the latches
+        card.egaRegister3cfValues[1]=0;
+        card.egaRegister3c5Values[2]=0x0e;
+        card.egaRegister3cfValues[8]=0x0f;
+        card.readMemory(0xa0000);
+        card.writeMemory(0xa0000,0x00);
+        assert_eq!(planeBytes(&card,0),[0xff,0x00,0xa0,0xa0]);
+    }
+
+    #[test]
+    fn writeMode0RotateAndLogicOps()
+    {
+        let mut card=newPlanarEga();
+        card.egaRegister3cfValues[3]=0x04;
+        card.writeMemory(0xa0001,0x0f);
+        assert_eq!(planeBytes(&card,1),[0xf0;4]);
+
+        let expected=[(0x00,0x3c),(0x08,0x30),(0x10,0x3c|0xf0),(0x18,0x3c^0xf0)];
+        for (gcReg3,result) in expected
+        {
+            setPlaneBytes(&mut card,1,[0xf0;4]);
+            card.egaRegister3cfValues[3]=gcReg3;
+            card.readMemory(0xa0001);
+            card.writeMemory(0xa0001,0x3c);
+            assert_eq!(planeBytes(&card,1),[result;4],"GC 03h {:02x}",gcReg3);
+        }
+    }
+
+    #[test]
+    fn writeMode1CopiesTheLatches()
+    {
+        let mut card=newPlanarEga();
+        setPlaneBytes(&mut card,0x10,[0x12,0x34,0x56,0x78]);
+        card.egaRegister3cfValues[5]=0x01;
+        card.egaRegister3cfValues[8]=0x00;
+        card.readMemory(0xa0010);
+        card.writeMemory(0xa0020,0xff);
+        assert_eq!(planeBytes(&card,0x20),[0x12,0x34,0x56,0x78]);
+    }
+
+    #[test]
+    fn writeMode2ColorThroughBitMask()
+    {
+        let mut card=newPlanarEga();
+        setPlaneBytes(&mut card,0,[0x00,0xff,0x00,0xff]);
+        card.egaRegister3cfValues[5]=0x02;
+        card.egaRegister3cfValues[8]=0x81;
+        card.readMemory(0xa0000);
+        card.writeMemory(0xa0000,0x05);
+        assert_eq!(planeBytes(&card,0),[0x81,0x7e,0x81,0x7e]);
+    }
+
+    #[test]
+    fn writeMode3SetResetMaskedByRotatedData()
+    {
+        let mut card=newPlanarEga();
+        card.egaRegister3cfValues[0]=0x0c;
+        card.egaRegister3cfValues[3]=0x01;
+        card.egaRegister3cfValues[5]=0x03;
+        card.egaRegister3cfValues[8]=0xf0;
+        card.readMemory(0xa0000);
+        card.writeMemory(0xa0000,0x3c);
+        assert_eq!(planeBytes(&card,0),[0x00,0x00,0x10,0x10]);
+    }
+
+    #[test]
+    fn readMode1ComparesColors()
+    {
+        let mut card=newPlanarEga();
+        // pixels from the left: colors 5, 5, 4, 1, 0, 14, 5, 13
+        setPlaneBytes(&mut card,0,[0xd3,0x04,0xe7,0x05]);
+        card.egaRegister3cfValues[5]=0x08;
+        card.egaRegister3cfValues[2]=0x05;
+        card.egaRegister3cfValues[7]=0x0f;
+        assert_eq!(card.readMemory(0xa0000),0xc2);
+
+        // plane 3 don't care: 13 matches as well
+        card.egaRegister3cfValues[7]=0x07;
+        assert_eq!(card.readMemory(0xa0000),0xc3);
+    }
+
+    #[test]
+    fn dacEntriesKeepTheir6Bits()
+    {
+        let mut card=newPlanarEga();
+        for val in 0..64
+        {
+            card.setDacEntry(val,val,63-val,val^0x2a);
+            assert_eq!(card.getDacEntry(val),(val,63-val,val^0x2a));
+        }
+
+        assert_eq!(vga::expandDacComponent(0x00),0x00);
+        assert_eq!(vga::expandDacComponent(0x3f),0xff);
+        assert_eq!(vga::expandDacComponent(0x20),0x82);
+        card.setDacEntry(1,0x3f,0,0x20);
+        assert_eq!(card.vgaPalette[1],0xff0082);
+    }
+
+    #[test]
+    fn dacPortsReadBackWhatWasWritten()
+    {
+        let mut card=newPlanarEga();
+        card.write0x3c8(0x10);
+        for val in [0x3f,0x40|0x15,0xff,0x01,0x02,0x03]
+        {
+            card.write0x3c9(val);
+        }
+        assert_eq!(card.read0x3c8(),0x12);
+
+        // the read index auto-increments too, and the top 2 bits of each component are gone
+        card.write0x3c7(0x10);
+        assert_eq!(card.read0x3c7(),0x03);
+        let mut readBack:Vec<u8>=Vec::new();
+        for _i in 0..6
+        {
+            readBack.push(card.read0x3c9());
         }
+        assert_eq!(readBack,[0x3f,0x15,0x3f,0x01,0x02,0x03]);
     }
 }
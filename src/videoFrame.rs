@@ -0,0 +1,65 @@
+/* rendered frame of the emulated display - dream86 */
+
+use image::{Rgb, RgbImage};
+
+use crate::vga::vga;
+
+pub struct videoFrame
+{
+    pub videoMode: u16,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u32>,
+    // overscan color, around the displayed area
+    pub borderColor: u32
+}
+
+impl videoFrame
+{
+    pub fn new() -> Self
+    {
+        videoFrame
+        {
+            videoMode: 0xffff,
+            width: 0,
+            height: 0,
+            pixels: Vec::new(),
+            borderColor: 0
+        }
+    }
+
+    // follows the video mode and resolution of the card, returns true if the frame had to be resized
+    pub fn fitVideomode(&mut self,pvga:&vga) -> bool
+    {
+        let (width,height)=pvga.getVideoResolution();
+        if (pvga.mode==self.videoMode) && (width==self.width) && (height==self.height)
+        {
+            return false;
+        }
+
+        self.videoMode=pvga.mode;
+        self.width=width;
+        self.height=height;
+        self.pixels=vec![0;(width as usize)*(height as usize)];
+        return true;
+    }
+
+    // writes the frame, at the guest resolution, to a PNG file
+    pub fn savePng(&self,fname:&str) -> bool
+    {
+        let mut img=RgbImage::new(self.width,self.height);
+        for (idx,pixel) in self.pixels.iter().enumerate()
+        {
+            let x=(idx as u32)%self.width;
+            let y=(idx as u32)/self.width;
+            img.put_pixel(x,y,Rgb([((pixel>>16)&0xff) as u8,((pixel>>8)&0xff) as u8,(pixel&0xff) as u8]));
+        }
+
+        if let Err(e)=img.save_with_format(fname,image::ImageFormat::Png)
+        {
+            eprintln!("Unable to save {} error:{}",fname,e);
+            return false;
+        }
+        return true;
+    }
+}
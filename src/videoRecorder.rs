@@ -0,0 +1,139 @@
+/* recording of the emulated display to a y4m video - dream86 */
+
+use std::io::prelude::*;
+use std::fs::File;
+
+use crate::videoFrame::videoFrame;
+
+/*
+    one frame is recorded at every emulated vertical retrace, that's 4166 instructions,
+    while the timer ticks 18.2 times per second every 10000 instructions:
+    10000*(1193182/65536)/4166 = 43.7 frames per second of emulated time
+
+    the output is an uncompressed YUV4MPEG2 stream (4:4:4), ready for ffmpeg:
+    ffmpeg -i dream86_0000.y4m -vf scale=iw*sar:ih dream86.mp4
+*/
+
+pub struct videoRecorder
+{
+    outFile: Option<File>,
+    pub fileName: String,
+    width: u32,
+    height: u32,
+    pub frameCount: u64
+}
+
+impl videoRecorder
+{
+    pub fn new() -> Self
+    {
+        videoRecorder
+        {
+            outFile: None,
+            fileName: String::from(""),
+            width: 0,
+            height: 0,
+            frameCount: 0
+        }
+    }
+
+    pub fn isRecording(&self) -> bool
+    {
+        return self.outFile.is_some();
+    }
+
+    pub fn start(&mut self,fname:&str) -> bool
+    {
+        let f = match File::create(fname) {
+            Ok(f) => f,
+            Err(e) => {
+                eprintln!("Unable to create video file {} error:{}",fname,e);
+                return false;
+            }
+        };
+
+        self.outFile=Some(f);
+        self.fileName=String::from(fname);
+        self.width=0;
+        self.height=0;
+        self.frameCount=0;
+        return true;
+    }
+
+    // every frame is written as soon as it's recorded, there's nothing to finalize
+    pub fn stop(&mut self)
+    {
+        self.outFile=None;
+    }
+
+    fn gcd(a:u32,b:u32) -> u32
+    {
+        if b==0 { return a; }
+        return Self::gcd(b,a%b);
+    }
+
+    // the stream size is the one of the first frame, with the sample aspect ratio of a 4:3 monitor
+    fn writeHeader(&mut self,frame:&videoFrame)
+    {
+        self.width=frame.width;
+        self.height=frame.height;
+
+        let aspectNum=4*self.height;
+        let aspectDen=3*self.width;
+        let div=Self::gcd(aspectNum,aspectDen);
+        let header=format!("YUV4MPEG2 W{} H{} F437:10 Ip A{}:{} C444\n",self.width,self.height,aspectNum/div,aspectDen/div);
+
+        if let Some(f)=&mut self.outFile
+        {
+            f.write_all(header.as_bytes()).ok();
+        }
+    }
+
+    // a frame with a different size (after a video mode switch) is stretched to the stream size
+    pub fn addFrame(&mut self,frame:&videoFrame)
+    {
+        if (!self.isRecording()) || (frame.width==0) || (frame.height==0) { return; }
+        if self.frameCount==0
+        {
+            self.writeHeader(frame);
+        }
+
+        let planeSize=(self.width as usize)*(self.height as usize);
+        let mut buf:Vec<u8>=vec![0;6+(planeSize*3)];
+        buf[0..6].copy_from_slice(b"FRAME\n");
+
+        let mut idx=0;
+        for y in 0..self.height
+        {
+            let srcy=(y*frame.height)/self.height;
+            for x in 0..self.width
+            {
+                let srcx=(x*frame.width)/self.width;
+                let pixel=frame.pixels[((srcy*frame.width)+srcx) as usize];
+                let r=((pixel>>16)&0xff) as i32;
+                let g=((pixel>>8)&0xff) as i32;
+                let b=(pixel&0xff) as i32;
+
+                // BT.601, studio range
+                buf[6+idx]=((((66*r)+(129*g)+(25*b)+128)>>8)+16) as u8;
+                buf[6+planeSize+idx]=((((-38*r)-(74*g)+(112*b)+128)>>8)+128) as u8;
+                buf[6+(planeSize*2)+idx]=((((112*r)-(94*g)-(18*b)+128)>>8)+128) as u8;
+                idx+=1;
+            }
+        }
+
+        let mut writeOk=false;
+        if let Some(f)=&mut self.outFile
+        {
+            writeOk=f.write_all(&buf).is_ok();
+        }
+
+        if !writeOk
+        {
+            eprintln!("Unable to write to video file {}, recording stopped",self.fileName);
+            self.stop();
+            return;
+        }
+        self.frameCount+=1;
+    }
+}
@@ -0,0 +1,183 @@
+/* scaling of the rendered frame to the window, with optional CRT effects - dream86 */
+
+use crate::videoFrame::videoFrame;
+
+#[derive(PartialEq,Clone,Copy)]
+pub enum scaleMode
+{
+    // the frame fills a 4:3 screen, like on a monitor
+    scaleAspect,
+    // every guest pixel is a block of whole window pixels
+    scaleInteger,
+}
+
+#[derive(PartialEq,Clone,Copy)]
+pub enum crtFilter
+{
+    filterNone,
+    filterScanlines,
+    filterCrt,
+}
+
+// the window opens at this size times the zoom
+const BASE_WINDOW_WIDTH:u32=640;
+const BASE_WINDOW_HEIGHT:u32=480;
+pub const MAX_ZOOM:u32=4;
+
+pub struct videoScaler
+{
+    pub mode: scaleMode,
+    pub zoom: u32,
+    pub filter: crtFilter,
+    pub showBorder: bool,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u32>
+}
+
+impl videoScaler
+{
+    pub fn new() -> Self
+    {
+        videoScaler
+        {
+            mode: scaleMode::scaleAspect,
+            zoom: 1,
+            filter: crtFilter::filterNone,
+            showBorder: false,
+            width: 0,
+            height: 0,
+            pixels: Vec::new()
+        }
+    }
+
+    pub fn getWindowSize(&self) -> (u32,u32)
+    {
+        return (BASE_WINDOW_WIDTH*self.zoom,BASE_WINDOW_HEIGHT*self.zoom);
+    }
+
+    // (x,y,width,height) of the picture inside an output of outWidth x outHeight, the rest is letterbox or border
+    pub fn getPictureRect(&self,frameWidth:u32,frameHeight:u32,outWidth:u32,outHeight:u32) -> (u32,u32,u32,u32)
+    {
+        if (frameWidth==0) || (frameHeight==0) { return (0,0,0,0); }
+
+        // the border takes 1/16 of the output on every side, like the overscan of a monitor
+        let mut availWidth=outWidth;
+        let mut availHeight=outHeight;
+        if self.showBorder
+        {
+            availWidth=(outWidth*7)/8;
+            availHeight=(outHeight*7)/8;
+        }
+
+        // 640x200 is too flat with square pixels, its lines are doubled
+        let lineMult=if ((frameWidth*10)/frameHeight)>=25 { 2 } else { 1 };
+        let intMult=std::cmp::min(availWidth/frameWidth,availHeight/(frameHeight*lineMult));
+
+        let picWidth;
+        let picHeight;
+        if (self.mode==scaleMode::scaleInteger) && (intMult>0)
+        {
+            picWidth=frameWidth*intMult;
+            picHeight=frameHeight*lineMult*intMult;
+        }
+        else if self.mode==scaleMode::scaleInteger
+        {
+            // the output is smaller than the frame, it gets shrunk keeping the pixels shape
+            picWidth=std::cmp::min(availWidth,(availHeight*frameWidth)/(frameHeight*lineMult));
+            picHeight=(picWidth*frameHeight*lineMult)/frameWidth;
+        }
+        else
+        {
+            picWidth=std::cmp::min(availWidth,(availHeight*4)/3);
+            picHeight=(picWidth*3)/4;
+        }
+
+        return ((outWidth-picWidth)/2,(outHeight-picHeight)/2,picWidth,picHeight);
+    }
+
+    pub fn nextMode(&mut self)
+    {
+        self.mode=if self.mode==scaleMode::scaleAspect { scaleMode::scaleInteger } else { scaleMode::scaleAspect };
+    }
+
+    pub fn nextFilter(&mut self)
+    {
+        self.filter=match self.filter {
+            crtFilter::filterNone => crtFilter::filterScanlines,
+            crtFilter::filterScanlines => crtFilter::filterCrt,
+            crtFilter::filterCrt => crtFilter::filterNone,
+        };
+    }
+
+    pub fn setZoom(&mut self,zoom:u32)
+    {
+        self.zoom=zoom.clamp(1,MAX_ZOOM);
+    }
+
+    // scales each 8 bits channel of an RGB pixel by num/256
+    fn dimPixel(pixel:u32,rnum:u32,gnum:u32,bnum:u32) -> u32
+    {
+        let r=((((pixel>>16)&0xff)*rnum)>>8)&0xff;
+        let g=((((pixel>>8)&0xff)*gnum)>>8)&0xff;
+        let b=(((pixel&0xff)*bnum)>>8)&0xff;
+        return (r<<16)|(g<<8)|b;
+    }
+
+    /*
+        nearest neighbour scaling into the picture rectangle, then the filter:
+        scanlines   the bottom half of every guest line is darker
+        crt         darker scanlines, plus an aperture grille (every window column
+                    favours one of red, green and blue)
+        scanlines need at least two window lines for each guest line, they are skipped otherwise
+        around the picture there's the border color, or black
+    */
+    pub fn scaleFrame(&mut self,frame:&videoFrame,outWidth:u32,outHeight:u32)
+    {
+        if (outWidth!=self.width) || (outHeight!=self.height)
+        {
+            self.width=outWidth;
+            self.height=outHeight;
+            self.pixels=vec![0;(outWidth as usize)*(outHeight as usize)];
+        }
+
+        let backColor=if self.showBorder { frame.borderColor } else { 0 };
+        for pixel in self.pixels.iter_mut()
+        {
+            *pixel=backColor;
+        }
+
+        let (picx,picy,picWidth,picHeight)=self.getPictureRect(frame.width,frame.height,outWidth,outHeight);
+        if (picWidth==0) || (picHeight==0) { return; }
+
+        let srcColumns:Vec<usize>=(0..picWidth).map(|x| ((x*frame.width)/picWidth) as usize).collect();
+        let hasScanlines=(self.filter!=crtFilter::filterNone) && (picHeight>=(frame.height*2));
+
+        for y in 0..picHeight
+        {
+            let srcRow=(((y*frame.height)/picHeight)*frame.width) as usize;
+            let dstRow=(((picy+y)*outWidth)+picx) as usize;
+            let isScanline=hasScanlines && ((((y*frame.height*2)/picHeight)&1)==1);
+
+            for x in 0..(picWidth as usize)
+            {
+                let mut pixel=frame.pixels[srcRow+srcColumns[x]];
+                if self.filter==crtFilter::filterScanlines
+                {
+                    if isScanline { pixel=Self::dimPixel(pixel,128,128,128); }
+                }
+                else if self.filter==crtFilter::filterCrt
+                {
+                    let lineLevel=if isScanline { 144 } else { 256 };
+                    let maskLevel=(lineLevel*3)/4;
+                    pixel=match x%3 {
+                        0 => Self::dimPixel(pixel,lineLevel,maskLevel,maskLevel),
+                        1 => Self::dimPixel(pixel,maskLevel,lineLevel,maskLevel),
+                        _ => Self::dimPixel(pixel,maskLevel,maskLevel,lineLevel),
+                    };
+                }
+                self.pixels[dstRow+x]=pixel;
+            }
+        }
+    }
+}
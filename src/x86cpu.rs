@@ -12,7 +12,6 @@
     
 */
 
-use std::process;
 use std::collections::HashMap;
 
 use rand::Rng;
@@ -20,6 +19,7 @@ use rand::Rng;
 use crate::vga::vga;
 use crate::machine::machine;
 use crate::fddController::fddController;
+use crate::emuLog;
 
 //
 
@@ -124,6 +124,7 @@ pub enum instructionType
     instrWait,
     instrFninit,
     instrFnstsw,
+    instrHlt,
 }
 
 pub struct decodedInstruction
@@ -161,7 +162,8 @@ pub struct x86cpu
     pub totInstructions: u64,
     decInstr: decodedInstruction,
     pub isIntPending: bool,
-    pub intPendingNum: u8
+    pub intPendingNum: u8,
+    pub isHalted: bool
 }
 
 //
@@ -212,6 +214,7 @@ impl x86cpu
                 decInstr: decIn,
                 isIntPending: false,
                 intPendingNum: 0,
+                isHalted: false,
             }
         }
         else
@@ -236,6 +239,7 @@ impl x86cpu
                 decInstr: decIn,
                 isIntPending: false,
                 intPendingNum: 0,
+                isHalted: false,
             }
 
         }
@@ -3089,6 +3093,7 @@ impl x86cpu
         else if it=="Wait" { return instructionType::instrWait; }
         else if it=="Fninit" { return instructionType::instrFninit; }
         else if it=="Fnstsw" { return instructionType::instrFnstsw; }
+        else if it=="Hlt" { return instructionType::instrHlt; }
         else if it=="SbbNMRR" { return instructionType::instrSbbNoModRegRm; }
         else { return instructionType::instrNone; }
     }
@@ -3143,6 +3148,7 @@ impl x86cpu
             0xfa => { return ["CLI","16","0","","","Cli","0"]; }
             0xfb => { return ["STI","16","0","","","Sti","0"]; }
             0xfd => { return ["STD","16","0","","","Std","0"]; }
+            0xf4 => { return ["HLT","16","0","","","Hlt","0"]; }
             // INC 16bit reg
             0x40 => { return ["INC AX","16","1","AX","","IncNMRR","0"]; }
             0x41 => { return ["INC CX","16","1","CX","","IncNMRR","0"]; }
@@ -4586,6 +4592,12 @@ impl x86cpu
             // do absolutely nothing
             self.ip+=self.decInstr.insLen as u16;
         }
+        else if self.decInstr.insType==instructionType::instrHlt
+        {
+            // the cpu sleeps until the next hardware interrupt
+            self.isHalted=true;
+            self.ip+=self.decInstr.insLen as u16;
+        }
         else if self.decInstr.insType==instructionType::instrSalc
         {
             if self.getCflag()
@@ -4849,8 +4861,7 @@ impl x86cpu
 
     fn abort(&self,s:&str)
     {
-        println!("bailing out due to {}...",s);
-        process::exit(0x0100);
+        emuLog::fatalError(&format!("bailing out due to {}...",s),emuLog::EXIT_ABORTED);
     }
 
     fn prepareDbgInfo(&self,cs:u16,ip:u16,pmachine:&mut machine,pvga:&mut vga) -> String
@@ -4894,6 +4905,7 @@ impl x86cpu
         if (!debugFlag) && (self.isIntPending) && (self.getIflag()==true)
         {
             self.isIntPending=false;
+            self.isHalted=false;
 
             let newip=pmachine.readMemory16(0x0,(self.intPendingNum as u16)*4,pvga);
             let newcs=pmachine.readMemory16(0x0,((self.intPendingNum as u16)*4)+2,pvga);
@@ -4912,6 +4924,17 @@ impl x86cpu
             tmpip=self.ip;
         }
 
+        if (!debugFlag) && self.isHalted
+        {
+            *bytesRead=1;
+            return format!("{:04x}:{:04x} HLT",self.cs,self.ip);
+        }
+
+        if (!debugFlag) && (self.cs==0) && (self.ip==0x7c00)
+        {
+            pmachine.bootSectorStarted(pdisk,pvga);
+        }
+
         let canDecode=self.dekode(pmachine,pvga,tmpcs,tmpip);
         if canDecode
         {